@@ -0,0 +1,335 @@
+//! An assembler for the mnemonics printed by [`disasm`](crate::disasm::disasm),
+//! handy for building small programs without encoding opcodes by hand.
+//!
+//! ```text
+//! start:  LD V0, 0            ; comments start with a semicolon
+//!         LD I, sprite
+//!         DRW V0, V0, 1
+//!         JP start
+//! sprite: DB 0xFF, 0b10000001
+//! ```
+//!
+//! Numbers are decimal, `0x` hex or `0b` binary, and anywhere a number goes
+//! a label can be used instead. `DB` and `DW` emit data bytes and words.
+//! Mnemonics, registers and labels are case-insensitive.
+
+use crate::cpu::DEFAULT_LOAD_ADDRESS;
+use std::{collections::HashMap, error::Error, fmt};
+
+/// Why a line couldn't be assembled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    /// The 1-based line the error is on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AssembleError {}
+
+/// Assembles `source` into a program to load at 0x200.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let statements = parse(source)?;
+
+    let mut labels = HashMap::new();
+    let mut address = DEFAULT_LOAD_ADDRESS as usize;
+    for statement in &statements {
+        for label in &statement.labels {
+            if labels.insert(label.clone(), address).is_some() {
+                return Err(statement.error(format!("duplicate label {label}")));
+            }
+        }
+        address += statement.size();
+    }
+
+    let assembler = Assembler { labels };
+    let mut program = Vec::new();
+    for statement in &statements {
+        let bytes = assembler
+            .encode(statement)
+            .map_err(|message| statement.error(message))?;
+        program.extend(bytes);
+    }
+    Ok(program)
+}
+
+/// One line of source, uppercased, with its comment removed.
+struct Statement {
+    line: usize,
+    labels: Vec<String>,
+    mnemonic: String,
+    operands: Vec<String>,
+}
+
+impl Statement {
+    /// How many bytes the statement assembles to, needed to place labels
+    /// before they can be resolved.
+    fn size(&self) -> usize {
+        match self.mnemonic.as_str() {
+            "" => 0,
+            "DB" => self.operands.len(),
+            "DW" => 2 * self.operands.len(),
+            "LD" if self.long_address().is_some() => 4,
+            _ => 2,
+        }
+    }
+
+    /// The address after `LD I, LONG`, which takes up the following word.
+    fn long_address(&self) -> Option<&str> {
+        match self.operands.as_slice() {
+            [i, long] if i == "I" => long.strip_prefix("LONG ").map(str::trim),
+            _ => None,
+        }
+    }
+
+    fn error(&self, message: String) -> AssembleError {
+        AssembleError {
+            line: self.line,
+            message,
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Statement>, AssembleError> {
+    let mut statements = Vec::new();
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let mut rest = text
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_uppercase();
+
+        let mut labels = Vec::new();
+        while let Some((label, after)) = rest.split_once(':') {
+            let label = label.trim();
+            if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(AssembleError {
+                    line,
+                    message: format!("invalid label {label}"),
+                });
+            }
+            labels.push(label.to_string());
+            rest = after.trim().to_string();
+        }
+
+        let (mnemonic, operands) = match rest.split_once(char::is_whitespace) {
+            Some((mnemonic, operands)) => (
+                mnemonic.to_string(),
+                operands.split(',').map(|s| s.trim().to_string()).collect(),
+            ),
+            None => (rest, Vec::new()),
+        };
+        statements.push(Statement {
+            line,
+            labels,
+            mnemonic,
+            operands,
+        });
+    }
+    Ok(statements)
+}
+
+struct Assembler {
+    labels: HashMap<String, usize>,
+}
+
+impl Assembler {
+    fn encode(&self, statement: &Statement) -> Result<Vec<u8>, String> {
+        let operands: Vec<&str> = statement.operands.iter().map(String::as_str).collect();
+        let opcode = match (statement.mnemonic.as_str(), operands.as_slice()) {
+            ("", []) => return Ok(Vec::new()),
+            ("DB", bytes) if !bytes.is_empty() => {
+                return bytes
+                    .iter()
+                    .map(|b| Ok(self.number(b, 0xFF)? as u8))
+                    .collect();
+            }
+            ("DW", words) if !words.is_empty() => {
+                let mut out = Vec::new();
+                for word in words {
+                    out.extend(self.number(word, 0xFFFF)?.to_be_bytes());
+                }
+                return Ok(out);
+            }
+            ("LD", ["I", _]) if statement.long_address().is_some() => {
+                let address = self.number(statement.long_address().unwrap(), 0xFFFF)?;
+                let mut out = vec![0xF0, 0x00];
+                out.extend(address.to_be_bytes());
+                return Ok(out);
+            }
+
+            ("SCD", [n]) => 0x00C0 | self.number(n, 0xF)?,
+            ("CLS", []) => 0x00E0,
+            ("RET", []) => 0x00EE,
+            ("SCR", []) => 0x00FB,
+            ("SCL", []) => 0x00FC,
+            ("EXIT", []) => 0x00FD,
+            ("LOW", []) => 0x00FE,
+            ("HIGH", []) => 0x00FF,
+            ("JP", ["V0", address]) => 0xB000 | self.number(address, 0xFFF)?,
+            ("JP", [address]) => 0x1000 | self.number(address, 0xFFF)?,
+            ("CALL", [address]) => 0x2000 | self.number(address, 0xFFF)?,
+            ("SE", [x, y]) if register(y).is_ok() => 0x5000 | xy(x, y)?,
+            ("SE", [x, nn]) => 0x3000 | self.xnn(x, nn)?,
+            ("SNE", [x, y]) if register(y).is_ok() => 0x9000 | xy(x, y)?,
+            ("SNE", [x, nn]) => 0x4000 | self.xnn(x, nn)?,
+            ("SAVE", [range]) => 0x5002 | register_range(range)?,
+            ("LOAD", [range]) => 0x5003 | register_range(range)?,
+            ("LD", ["I", address]) => 0xA000 | self.number(address, 0xFFF)?,
+            ("LD", ["DT", x]) => 0xF015 | register(x)? << 8,
+            ("LD", ["ST", x]) => 0xF018 | register(x)? << 8,
+            ("LD", ["F", x]) => 0xF029 | register(x)? << 8,
+            ("LD", ["HF", x]) => 0xF030 | register(x)? << 8,
+            ("LD", ["B", x]) => 0xF033 | register(x)? << 8,
+            ("LD", ["[I]", x]) => 0xF055 | register(x)? << 8,
+            ("LD", ["R", x]) => 0xF075 | register(x)? << 8,
+            ("LD", [x, "DT"]) => 0xF007 | register(x)? << 8,
+            ("LD", [x, "K"]) => 0xF00A | register(x)? << 8,
+            ("LD", [x, "[I]"]) => 0xF065 | register(x)? << 8,
+            ("LD", [x, "R"]) => 0xF085 | register(x)? << 8,
+            ("LD", [x, y]) if register(y).is_ok() => 0x8000 | xy(x, y)?,
+            ("LD", [x, nn]) => 0x6000 | self.xnn(x, nn)?,
+            ("ADD", ["I", x]) => 0xF01E | register(x)? << 8,
+            ("ADD", [x, y]) if register(y).is_ok() => 0x8004 | xy(x, y)?,
+            ("ADD", [x, nn]) => 0x7000 | self.xnn(x, nn)?,
+            ("OR", [x, y]) => 0x8001 | xy(x, y)?,
+            ("AND", [x, y]) => 0x8002 | xy(x, y)?,
+            ("XOR", [x, y]) => 0x8003 | xy(x, y)?,
+            ("SUB", [x, y]) => 0x8005 | xy(x, y)?,
+            ("SHR", [x, y]) => 0x8006 | xy(x, y)?,
+            ("SUBN", [x, y]) => 0x8007 | xy(x, y)?,
+            ("SHL", [x, y]) => 0x800E | xy(x, y)?,
+            ("RND", [x, nn]) => 0xC000 | self.xnn(x, nn)?,
+            ("DRW", [x, y, n]) => 0xD000 | xy(x, y)? | self.number(n, 0xF)?,
+            ("SKP", [x]) => 0xE09E | register(x)? << 8,
+            ("SKNP", [x]) => 0xE0A1 | register(x)? << 8,
+            ("PLANE", [n]) => 0xF001 | self.number(n, 0xF)? << 8,
+            ("AUDIO", []) => 0xF002,
+            ("PITCH", [x]) => 0xF03A | register(x)? << 8,
+            (mnemonic, operands) => {
+                return Err(format!(
+                    "unknown instruction {mnemonic} {}",
+                    operands.join(", ")
+                ))
+            }
+        };
+        Ok(opcode.to_be_bytes().to_vec())
+    }
+
+    fn xnn(&self, x: &str, nn: &str) -> Result<u16, String> {
+        Ok(register(x)? << 8 | self.number(nn, 0xFF)?)
+    }
+
+    /// Parses a number or looks up a label, checking that it's at most `max`.
+    fn number(&self, s: &str, max: u16) -> Result<u16, String> {
+        let value = if let Some(hex) = s.strip_prefix("0X") {
+            usize::from_str_radix(hex, 16).map_err(|e| format!("{s}: {e}"))?
+        } else if let Some(binary) = s.strip_prefix("0B") {
+            usize::from_str_radix(binary, 2).map_err(|e| format!("{s}: {e}"))?
+        } else if s.starts_with(|c: char| c.is_ascii_digit()) {
+            s.parse().map_err(|e| format!("{s}: {e}"))?
+        } else {
+            *self
+                .labels
+                .get(s)
+                .ok_or_else(|| format!("unknown label {s}"))?
+        };
+
+        if value > max as usize {
+            return Err(format!("{s} is larger than 0x{max:X}"));
+        }
+        Ok(value as u16)
+    }
+}
+
+/// Parses `V0` through `VF`.
+fn register(s: &str) -> Result<u16, String> {
+    s.strip_prefix('V')
+        .filter(|digit| digit.len() == 1)
+        .and_then(|digit| u16::from_str_radix(digit, 16).ok())
+        .ok_or_else(|| format!("expected a register, found {s}"))
+}
+
+fn xy(x: &str, y: &str) -> Result<u16, String> {
+    Ok(register(x)? << 8 | register(y)? << 4)
+}
+
+/// Parses `Vx-Vy` as used by `SAVE` and `LOAD`.
+fn register_range(s: &str) -> Result<u16, String> {
+    let (x, y) = s
+        .split_once('-')
+        .ok_or_else(|| format!("expected a register range, found {s}"))?;
+    xy(x.trim(), y.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::{disasm, disasm_with_next};
+
+    #[test]
+    fn disassembly_assembles_back_to_the_same_opcode() {
+        let opcodes = [
+            0x00C3, 0x00E0, 0x00EE, 0x00FB, 0x00FC, 0x00FD, 0x00FE, 0x00FF, 0x1234, 0x2ABC, 0x3A12,
+            0x4B34, 0x5120, 0x5122, 0x5123, 0x6CFF, 0x7D01, 0x8120, 0x8121, 0x8122, 0x8123, 0x8124,
+            0x8125, 0x8126, 0x8127, 0x812E, 0x9120, 0xA345, 0xB456, 0xC70F, 0xD12F, 0xE39E, 0xE4A1,
+            0xF201, 0xF002, 0xF507, 0xF60A, 0xF715, 0xF818, 0xF91E, 0xFA29, 0xFB30, 0xFC33, 0xFD3A,
+            0xFE55, 0xFF65, 0xF075, 0xF185, 0xFFFF,
+        ];
+        for opcode in opcodes {
+            let text = disasm(opcode);
+            assert_eq!(assemble(&text), Ok(opcode.to_be_bytes().to_vec()), "{text}");
+        }
+    }
+
+    #[test]
+    fn ld_i_long_disassembly_assembles_back() {
+        let (text, size) = disasm_with_next(0xF000, 0x1234);
+        assert_eq!(size, 4);
+        assert_eq!(assemble(&text), Ok(vec![0xF0, 0x00, 0x12, 0x34]));
+    }
+
+    #[test]
+    fn labels_resolve_before_they_are_defined() {
+        let program = assemble("JP end\nCLS\nend: JP end").unwrap();
+        assert_eq!(program, [0x12, 0x04, 0x00, 0xE0, 0x12, 0x04]);
+    }
+
+    #[test]
+    fn db_and_dw_emit_data() {
+        let program = assemble("DB 1, 0x2, 0b11\nDW 0x1234, data\ndata: DB 0xFF").unwrap();
+        assert_eq!(program, [0x01, 0x02, 0x03, 0x12, 0x34, 0x02, 0x07, 0xFF]);
+    }
+
+    #[test]
+    fn ld_i_long_takes_two_words() {
+        let program = assemble("LD I, LONG data\nJP data\ndata: DB 0xAA").unwrap();
+        assert_eq!(program, [0xF0, 0x00, 0x02, 0x06, 0x12, 0x06, 0xAA]);
+    }
+
+    #[test]
+    fn ld_i_long_without_an_address_is_an_error() {
+        assert_eq!(assemble("LD I, LONG").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn unknown_instruction_reports_its_line() {
+        let error = assemble("CLS\n\nFOO V1").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(error.message, "unknown instruction FOO V1");
+    }
+
+    #[test]
+    fn duplicate_label_reports_its_line() {
+        let error = assemble("a: CLS\nb: RET\na: RET").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(error.message, "duplicate label A");
+    }
+}
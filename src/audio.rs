@@ -0,0 +1,150 @@
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    Stream,
+};
+use serde::Deserialize;
+use std::{
+    error::Error,
+    f32::consts::PI,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+pub const DEFAULT_TONE_HZ: f32 = 329.0;
+
+pub const VOLUME_STEP: f32 = 0.1;
+
+/// XO-CHIP plays audio patterns at 4000 bits per second at the default pitch.
+const PATTERN_RATE_HZ: f32 = 4000.0;
+const PATTERN_BITS: f32 = 128.0;
+
+/// How long the beep takes to fade in and out, short enough to sound
+/// instant but long enough that it doesn't click.
+const RAMP_SECS: f32 = 0.005;
+
+/// Settings shared with the audio callback that can change while playing.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct AudioParams {
+    /// Whether the beep should be sounding. It fades in and out rather than
+    /// starting and stopping at once.
+    pub playing: bool,
+    /// From 0.0 to 1.0.
+    pub volume: f32,
+    pub muted: bool,
+    /// Multiplies the frequency of the tone, set from the XO-CHIP pitch.
+    pub pitch_scale: f32,
+    /// The XO-CHIP audio pattern, played instead of the tone if set.
+    pub pattern: Option<[u8; 16]>,
+}
+
+/// The shape of the beep.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+}
+
+impl Waveform {
+    /// The amplitude at `phase`, which goes from 0 to 1 over one period.
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Self::Sine => (phase * 2.0 * PI).sin(),
+            Self::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Self::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Self::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sine" => Ok(Self::Sine),
+            "square" => Ok(Self::Square),
+            "triangle" => Ok(Self::Triangle),
+            "sawtooth" => Ok(Self::Sawtooth),
+            _ => Err(format!(
+                "invalid waveform {s}: expected sine, square, triangle or sawtooth"
+            )),
+        }
+    }
+}
+
+/// The bit of `pattern` at `phase`, which goes from 0 to 1 over all 128
+/// bits, as a full-scale square wave sample.
+fn pattern_sample(pattern: &[u8; 16], phase: f32) -> f32 {
+    let bit = (phase * PATTERN_BITS) as usize % 128;
+    if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+/// Opens the default output device with a stream that plays the beep while
+/// `params` says it's playing.
+pub fn build_stream(
+    tone_hz: f32,
+    waveform: Waveform,
+    params: Arc<Mutex<AudioParams>>,
+) -> Result<Stream, Box<dyn Error>> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("unable to get output device")?;
+    let config = device.default_output_config()?.config();
+
+    let sample_rate = config.sample_rate.0 as f32;
+    let tone_step = tone_hz / sample_rate;
+    let pattern_step = PATTERN_RATE_HZ / PATTERN_BITS / sample_rate;
+    let ramp_step = 1.0 / (RAMP_SECS * sample_rate);
+    let channels = config.channels as usize;
+    // kept outside the callback so the wave continues across buffers, and
+    // advanced once per frame so every channel plays the same sample
+    let mut phase = 0f32;
+    // how far the beep has faded in, from silent at 0 to full volume at 1
+    let mut envelope = 0f32;
+    let stream = device.build_output_stream(
+        &config,
+        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+            let params = *params.lock().unwrap();
+            let volume = if params.muted { 0.0 } else { params.volume };
+
+            let step = match params.pattern {
+                Some(_) => pattern_step,
+                None => tone_step,
+            };
+            for frame in data.chunks_mut(channels) {
+                let sample = match &params.pattern {
+                    Some(pattern) => pattern_sample(pattern, phase),
+                    None => waveform.sample(phase),
+                };
+                envelope = if params.playing {
+                    (envelope + ramp_step).min(1.0)
+                } else {
+                    (envelope - ramp_step).max(0.0)
+                };
+                frame.fill(sample * volume * envelope);
+                phase = (phase + step * params.pitch_scale).fract();
+            }
+        },
+        |e| {
+            panic!("{e}");
+        },
+        None,
+    )?;
+    stream.play()?;
+
+    Ok(stream)
+}
@@ -0,0 +1,196 @@
+use crate::{
+    audio::Waveform,
+    config::{self, QuirkOverrides},
+    debugger,
+    quirks::Platform,
+};
+use clap::Parser;
+use minifb::Scale;
+use std::path::PathBuf;
+
+/// A CHIP-8, SUPER-CHIP and XO-CHIP emulator.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// ROMs to run: files, directories of ROMs to pick from, `-` for stdin,
+    /// or HTTP URLs with the net feature. N and B switch to the next and
+    /// previous ROM
+    #[arg(required = true, value_name = "ROM")]
+    pub roms: Vec<String>,
+
+    /// Read settings from a TOML file; other options override it
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Quirks, memory and speed of a platform: chip8, vip, schip or xochip
+    #[arg(long)]
+    pub platform: Option<Platform>,
+
+    #[command(flatten)]
+    pub quirks: QuirkOverrides,
+
+    /// Instructions run per 60 Hz frame instead of the platform's speed
+    #[arg(long, value_name = "N", value_parser = config::parse_ipf)]
+    pub ipf: Option<u32>,
+
+    /// Seed the random number generator for reproducible runs
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Foreground color
+    #[arg(long, value_name = "RRGGBB", value_parser = config::parse_color)]
+    pub fg: Option<u32>,
+
+    /// Background color
+    #[arg(long, value_name = "RRGGBB", value_parser = config::parse_color)]
+    pub bg: Option<u32>,
+
+    /// Window size as a multiple of the display: 1, 2, 4, 8, 16, or fit to
+    /// size it to the screen
+    #[arg(long, default_value = "8", value_parser = parse_scale)]
+    pub scale: Scale,
+
+    /// Draw lines between the CHIP-8 pixels when they're large enough
+    #[arg(long)]
+    pub grid: bool,
+
+    /// Fade pixels out over a few frames instead of turning them off at once
+    #[arg(long)]
+    pub ghosting: bool,
+
+    /// Read the keys for CHIP-8 keys 0 to F from a file
+    #[arg(long, value_name = "PATH")]
+    pub keymap: Option<PathBuf>,
+
+    /// Pitch of the beep in hertz
+    #[arg(long, value_name = "HZ")]
+    pub tone: Option<f32>,
+
+    /// Shape of the beep: sine, square, triangle or sawtooth
+    #[arg(long)]
+    pub wave: Option<Waveform>,
+
+    /// Don't open an audio device. Without one, the emulator plays silently
+    /// anyway
+    #[arg(long)]
+    pub no_audio: bool,
+
+    /// Read the hex digit sprites from a file of 80 bytes, or 240 with the
+    /// big font
+    #[arg(long, value_name = "PATH")]
+    pub font: Option<PathBuf>,
+
+    /// Fill memory below the program from 0x000 with up to 512 bytes read
+    /// from a file. The font is still loaded on top of it
+    #[arg(long, value_name = "PATH")]
+    pub load_interpreter: Option<PathBuf>,
+
+    /// Load the ROM at ADDR, in hex, and start running it there instead of
+    /// at 0x200
+    #[arg(long, value_name = "ADDR", value_parser = debugger::parse_address)]
+    pub load_addr: Option<u16>,
+
+    /// Frames of history to keep for rewinding, 0 to turn rewinding off
+    #[arg(long, value_name = "FRAMES")]
+    pub rewind: Option<usize>,
+
+    /// Print the ROM's instructions instead of running it
+    #[arg(long, conflicts_with = "bench")]
+    pub disassemble: bool,
+
+    /// Print LEN bytes of memory from START, both in hex, as hex and ASCII
+    /// instead of running the ROM
+    #[arg(long, num_args = 2, value_names = ["START", "LEN"], value_parser = debugger::parse_address)]
+    pub dump_mem: Option<Vec<u16>>,
+
+    /// Draw LEN bytes of memory from START, both in hex, as rows of 8-pixel
+    /// sprites instead of running the ROM
+    #[arg(long, num_args = 2, value_names = ["START", "LEN"], value_parser = debugger::parse_address)]
+    pub view_sprites: Option<Vec<u16>>,
+
+    /// Overwrite the ROM's bytes at ADDR with BYTES, given in hex like
+    /// `A2F0`, before running it. May be given more than once
+    #[arg(long, num_args = 2, value_names = ["ADDR", "BYTES"])]
+    pub patch: Vec<String>,
+
+    /// Run COUNT instructions without a window and report the speed
+    #[arg(long, value_name = "COUNT")]
+    pub bench: Option<u64>,
+
+    /// Play in the terminal instead of a window, without sound
+    #[arg(long, conflicts_with_all = ["disassemble", "dump_mem", "view_sprites", "bench", "debug", "step"])]
+    pub tui: bool,
+
+    /// Pause before every instruction and read debugger commands from stdin
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Reload and restart the ROM whenever its file changes
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Start paused and run one instruction each time Space is pressed
+    #[arg(long)]
+    pub step: bool,
+
+    /// Stop on invalid instructions instead of skipping them
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Warn about writes to memory that has already been executed as code
+    #[arg(long)]
+    pub warn_smc: bool,
+
+    /// Stop after executing this many instructions and print the screen
+    /// hash
+    #[arg(long, value_name = "N", conflicts_with_all = ["tui", "bench"])]
+    pub max_instructions: Option<u64>,
+
+    /// When the emulator exits, save the screen as a PNG, or print it as
+    /// text if the path is -
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["tui", "bench"])]
+    pub dump_screen_on_exit: Option<PathBuf>,
+
+    /// Run without a window or sound and with no keys held until the program
+    /// exits or --max-instructions is reached
+    #[arg(long, conflicts_with_all = ["disassemble", "dump_mem", "view_sprites", "bench", "tui", "debug", "step"])]
+    pub headless: bool,
+
+    /// Run the ROM, which should be Timendus' quirks test, without a window
+    /// for the selected platform and print whether each category passed
+    #[arg(long, conflicts_with_all = ["disassemble", "dump_mem", "view_sprites", "bench", "tui", "headless", "debug", "step"])]
+    pub quirk_report: bool,
+
+    /// Print the position and size of every sprite drawn and whether it
+    /// collided
+    #[arg(long)]
+    pub log_collisions: bool,
+
+    /// Append every executed instruction to a file
+    #[arg(long, value_name = "PATH")]
+    pub trace: Option<PathBuf>,
+
+    /// Count the instructions executed by kind and print a summary on exit
+    #[arg(long)]
+    pub profile: bool,
+
+    /// Write the keys pressed on every frame to a file
+    #[arg(long, value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Play back keys written by --record instead of reading the keyboard
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+}
+
+fn parse_scale(s: &str) -> Result<Scale, String> {
+    match s {
+        "1" => Ok(Scale::X1),
+        "2" => Ok(Scale::X2),
+        "4" => Ok(Scale::X4),
+        "8" => Ok(Scale::X8),
+        "16" => Ok(Scale::X16),
+        "fit" => Ok(Scale::FitScreen),
+        _ => Err(format!("invalid scale {s}: expected 1, 2, 4, 8, 16 or fit")),
+    }
+}
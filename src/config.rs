@@ -0,0 +1,287 @@
+use crate::{
+    audio::{self, Waveform},
+    cpu::{self, Cpu},
+    database::RomInfo,
+    font::{self, Font},
+    gamepad::GamepadMap,
+    keymap::{self, KEY_MAPPINGS},
+    quirks::{Platform, Quirks},
+};
+use clap::Args;
+use minifb::Key;
+use serde::{de, Deserialize, Deserializer};
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_FG: u32 = 0xFFFFFF;
+pub const DEFAULT_BG: u32 = 0x000000;
+/// How many frames run per frame while fast forwarding.
+pub const DEFAULT_TURBO: u32 = 8;
+/// Ten seconds of rewind at 60 frames per second.
+pub const DEFAULT_REWIND_FRAMES: usize = 600;
+
+/// Emulator settings, read from a TOML file. Anything left out keeps its
+/// default, and command line flags override what's in the file. Quirks and
+/// speed start from the platform's, which comes from the ROM database if
+/// the ROM is known and no platform is given.
+///
+/// ```toml
+/// platform = "schip"
+/// ipf = 15
+/// fg = "33FF33"
+/// bg = "001100"
+/// tone_hz = 440.0
+/// waveform = "square"
+/// volume = 0.5
+/// ghosting = true
+/// turbo = 8
+/// rewind_frames = 600
+/// font = "fonts/dream6800.bin"
+/// font_address = 0x000
+/// interpreter = "roms/vip-interpreter.bin"
+/// load_address = 0x200
+/// keymap = ["X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V"]
+///
+/// [gamepad]
+/// up = 1
+/// down = 4
+///
+/// [quirks]
+/// shift_uses_vy = false
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub platform: Option<Platform>,
+    pub quirks: QuirkOverrides,
+    #[serde(deserialize_with = "deserialize_ipf")]
+    pub ipf: Option<u32>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub fg: u32,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub bg: u32,
+    pub tone_hz: f32,
+    pub waveform: Waveform,
+    pub volume: f32,
+    pub ghosting: bool,
+    pub turbo: u32,
+    pub rewind_frames: usize,
+    pub font: Option<PathBuf>,
+    pub font_address: u16,
+    pub interpreter: Option<PathBuf>,
+    pub load_address: u16,
+    #[serde(deserialize_with = "deserialize_keymap")]
+    pub keymap: [Key; 16],
+    pub gamepad: GamepadMap,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        Ok(toml::from_str(&contents)?)
+    }
+
+    /// Uses the platform and speed recommended for a known ROM, unless a
+    /// platform was already chosen.
+    pub fn apply_rom_info(&mut self, rom: &RomInfo) {
+        if self.platform.is_none() {
+            self.platform = Some(rom.platform);
+            self.ipf.get_or_insert(rom.ipf());
+        }
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform.unwrap_or_default()
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks.apply(self.platform().quirks())
+    }
+
+    pub fn ipf(&self) -> u32 {
+        self.ipf.unwrap_or(self.platform().ipf())
+    }
+
+    /// The font read from the font file if there is one, otherwise the
+    /// built-in font, at the configured address.
+    pub fn font(&self) -> Result<Font, Box<dyn Error>> {
+        let font = match &self.font {
+            Some(path) => Font::new(&fs::read(path)?, self.font_address)?,
+            None => Font::default().at(self.font_address)?,
+        };
+        Ok(font)
+    }
+
+    /// A machine running `program` from the configured load address with
+    /// the configured quirks, font and interpreter.
+    pub fn cpu(&self, program: &[u8], seed: Option<u64>) -> Result<Cpu, Box<dyn Error>> {
+        let mut cpu = Cpu::new_at(program, self.quirks(), seed, self.load_address)?;
+        cpu.set_font(self.font()?);
+        if let Some(path) = &self.interpreter {
+            cpu.set_interpreter(&fs::read(path)?)?;
+        }
+        Ok(cpu)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            platform: None,
+            quirks: QuirkOverrides::default(),
+            ipf: None,
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG,
+            tone_hz: audio::DEFAULT_TONE_HZ,
+            waveform: Waveform::default(),
+            volume: 1.0,
+            ghosting: false,
+            turbo: DEFAULT_TURBO,
+            rewind_frames: DEFAULT_REWIND_FRAMES,
+            font: None,
+            font_address: font::DEFAULT_ADDRESS,
+            interpreter: None,
+            load_address: cpu::DEFAULT_LOAD_ADDRESS,
+            keymap: KEY_MAPPINGS,
+            gamepad: GamepadMap::default(),
+        }
+    }
+}
+
+/// Quirks that differ from the platform's, from the `[quirks]` table or the
+/// command line. See [`Quirks`] for what each one does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Args)]
+#[serde(default, deny_unknown_fields)]
+#[command(next_help_heading = "Quirks")]
+pub struct QuirkOverrides {
+    /// FX55 and FX65 leave I past the last register
+    #[arg(long, value_name = "BOOL")]
+    pub load_store_increments_i: Option<bool>,
+    /// 8XY6 and 8XYE shift VY instead of VX
+    #[arg(long, value_name = "BOOL")]
+    pub shift_uses_vy: Option<bool>,
+    /// 8XY6 and 8XYE write VF before the result
+    #[arg(long, value_name = "BOOL")]
+    pub shift_sets_flag_first: Option<bool>,
+    /// DXYN waits for vertical blank
+    #[arg(long, value_name = "BOOL")]
+    pub display_wait: Option<bool>,
+    /// BNNN jumps to XNN plus VX
+    #[arg(long, value_name = "BOOL")]
+    pub jump_offset_uses_vx: Option<bool>,
+    /// DXYN wraps sprites around the screen edges
+    #[arg(long, value_name = "BOOL")]
+    pub wrap_sprites: Option<bool>,
+    /// DXYN sets VF to the number of collided or clipped rows
+    #[arg(long, value_name = "BOOL")]
+    pub count_collided_rows: Option<bool>,
+    /// Taller sprites use up more of the frame
+    #[arg(long, value_name = "BOOL")]
+    pub draw_timing: Option<bool>,
+    /// Nested subroutine calls allowed
+    #[arg(long, value_name = "N")]
+    pub stack_size: Option<usize>,
+    /// 64 KB of memory instead of 4 KB
+    #[arg(long, value_name = "BOOL")]
+    pub extended_memory: Option<bool>,
+    /// Run XO-CHIP's extra opcodes
+    #[arg(long, value_name = "BOOL")]
+    pub xo_chip_opcodes: Option<bool>,
+}
+
+impl QuirkOverrides {
+    /// These overrides, falling back to `other`'s where these leave a quirk
+    /// alone.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            load_store_increments_i: self
+                .load_store_increments_i
+                .or(other.load_store_increments_i),
+            shift_uses_vy: self.shift_uses_vy.or(other.shift_uses_vy),
+            shift_sets_flag_first: self.shift_sets_flag_first.or(other.shift_sets_flag_first),
+            display_wait: self.display_wait.or(other.display_wait),
+            jump_offset_uses_vx: self.jump_offset_uses_vx.or(other.jump_offset_uses_vx),
+            wrap_sprites: self.wrap_sprites.or(other.wrap_sprites),
+            count_collided_rows: self.count_collided_rows.or(other.count_collided_rows),
+            draw_timing: self.draw_timing.or(other.draw_timing),
+            stack_size: self.stack_size.or(other.stack_size),
+            extended_memory: self.extended_memory.or(other.extended_memory),
+            xo_chip_opcodes: self.xo_chip_opcodes.or(other.xo_chip_opcodes),
+        }
+    }
+
+    pub fn apply(&self, quirks: Quirks) -> Quirks {
+        Quirks {
+            load_store_increments_i: self
+                .load_store_increments_i
+                .unwrap_or(quirks.load_store_increments_i),
+            shift_uses_vy: self.shift_uses_vy.unwrap_or(quirks.shift_uses_vy),
+            shift_sets_flag_first: self
+                .shift_sets_flag_first
+                .unwrap_or(quirks.shift_sets_flag_first),
+            display_wait: self.display_wait.unwrap_or(quirks.display_wait),
+            jump_offset_uses_vx: self
+                .jump_offset_uses_vx
+                .unwrap_or(quirks.jump_offset_uses_vx),
+            wrap_sprites: self.wrap_sprites.unwrap_or(quirks.wrap_sprites),
+            count_collided_rows: self
+                .count_collided_rows
+                .unwrap_or(quirks.count_collided_rows),
+            draw_timing: self.draw_timing.unwrap_or(quirks.draw_timing),
+            stack_size: self.stack_size.unwrap_or(quirks.stack_size),
+            extended_memory: self.extended_memory.unwrap_or(quirks.extended_memory),
+            xo_chip_opcodes: self.xo_chip_opcodes.unwrap_or(quirks.xo_chip_opcodes),
+        }
+    }
+}
+
+/// Parses a color written as `RRGGBB`, optionally with a leading `#`.
+pub fn parse_color(s: &str) -> Result<u32, String> {
+    let hex = s.trim_start_matches('#');
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid color {s}: expected RRGGBB"));
+    }
+    Ok(u32::from_str_radix(hex, 16).unwrap())
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_color(&s).map_err(de::Error::custom)
+}
+
+/// Parses a speed in instructions per frame.
+pub fn parse_ipf(s: &str) -> Result<u32, String> {
+    let ipf = s.parse().map_err(|e| format!("invalid ipf {s}: {e}"))?;
+    check_ipf(ipf)
+}
+
+/// Checks that a speed runs at least one instruction per frame, since the
+/// machine would never get anywhere at 0.
+fn check_ipf(ipf: u32) -> Result<u32, String> {
+    if ipf == 0 {
+        return Err("invalid ipf 0: expected at least 1".to_string());
+    }
+    Ok(ipf)
+}
+
+fn deserialize_ipf<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    let ipf = u32::deserialize(deserializer)?;
+    check_ipf(ipf).map(Some).map_err(de::Error::custom)
+}
+
+fn deserialize_keymap<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[Key; 16], D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    if names.len() != 16 {
+        return Err(de::Error::invalid_length(names.len(), &"16 keys"));
+    }
+
+    let mut mappings = KEY_MAPPINGS;
+    for (mapping, name) in mappings.iter_mut().zip(&names) {
+        *mapping = keymap::parse_key(name)
+            .ok_or_else(|| de::Error::custom(format!("unknown key {name}")))?;
+    }
+    Ok(mappings)
+}
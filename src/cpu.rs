@@ -0,0 +1,1115 @@
+use crate::{
+    display::Display,
+    error::Chip8Error,
+    font::Font,
+    hooks::{Chip8Hooks, NoHooks},
+    keypad::Keypad,
+    quirks::Quirks,
+};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Where programs are loaded and start running unless told otherwise.
+pub const DEFAULT_LOAD_ADDRESS: u16 = 0x200;
+
+/// The most bytes of program that fit after 0x200 in the largest memory,
+/// XO-CHIP's 64 KB.
+pub const MAX_PROGRAM_SIZE: usize = 0x10000 - DEFAULT_LOAD_ADDRESS as usize;
+
+/// The pitch that plays audio at XO-CHIP's base rate of 4000 Hz.
+pub const DEFAULT_PITCH: u8 = 64;
+
+/// With the draw timing quirk, every this many sprite rows take as long as
+/// another instruction.
+const ROWS_PER_INSTRUCTION: usize = 4;
+
+/// What changed while executing a single instruction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StepResult {
+    /// The screen was modified and needs to be redrawn.
+    pub redraw: bool,
+    /// The sound timer went from zero to non-zero or back.
+    pub sound_toggled: bool,
+    /// The rest of the frame should be skipped to wait for vertical blank.
+    /// Only `DXYN` does this, and only with the display wait quirk.
+    pub vblank: bool,
+    /// The RPL user flags were written and should be persisted.
+    pub flags_changed: bool,
+    /// The first write to a watched address, if there was one.
+    pub watch: Option<Watch>,
+    /// The first write to an address that has been executed, if there was
+    /// one and self-modifying code is being tracked.
+    pub self_modified: Option<Watch>,
+    /// The sprite drawn by `DXYN`, if that's what was executed.
+    pub draw: Option<Draw>,
+    /// `00FD` asked the interpreter to exit. The program counter stays on
+    /// it, so stepping again just exits again.
+    pub exit: bool,
+    /// How many of the frame's instructions this one used up, which is 1
+    /// unless it drew a sprite with the draw timing quirk.
+    pub cost: u32,
+}
+
+/// A write to an address in [`Cpu::watchpoints`], or to code that has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watch {
+    /// The instruction that did the write.
+    pub pc: u16,
+    pub address: u16,
+    pub old: u8,
+    pub new: u8,
+}
+
+/// A sprite drawn by `DXYN`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Draw {
+    /// The instruction that drew it.
+    pub pc: u16,
+    /// The top left corner, after wrapping to the screen.
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+    /// What `VF` was set to, non-zero if a pixel was turned off.
+    pub vf: u8,
+}
+
+/// A snapshot of everything needed to resume a [`Cpu`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuState {
+    pub mem: Vec<u8>,
+    pub reg: [u8; 16],
+    pub stack: Vec<u16>,
+    pub pc: u16,
+    pub idx: u16,
+    pub delay: u8,
+    pub sound: u8,
+    /// XO-CHIP audio pitch set by `FX3A`, 64 for the default rate.
+    pub pitch: u8,
+    /// XO-CHIP audio pattern loaded by `F002`.
+    pub pattern: Option<[u8; 16]>,
+    pub screen: Display,
+}
+
+/// The CHIP-8 machine without any windowing or audio attached.
+pub struct Cpu {
+    pub mem: Vec<u8>,
+    pub reg: [u8; 16],
+    pub stack: Vec<u16>,
+    pub pc: u16,
+    pub idx: u16,
+    pub delay: u8,
+    pub sound: u8,
+    /// XO-CHIP audio pitch set by `FX3A`, 64 for the default rate.
+    pub pitch: u8,
+    /// XO-CHIP audio pattern loaded by `F002`, 128 one-bit samples played
+    /// at the pitch's rate instead of the beep. `None` until one is loaded.
+    pub pattern: Option<[u8; 16]>,
+    pub screen: Display,
+    pub quirks: Quirks,
+    /// SUPER-CHIP's RPL user flags, which survive resets and are meant to be
+    /// kept between runs.
+    pub flags: [u8; 8],
+    font: Font,
+    // loaded below the font at 0x000 on every reset
+    interpreter: Vec<u8>,
+    // copied to the load address, where it starts running, on every reset
+    program: Vec<u8>,
+    load_address: u16,
+    /// Addresses whose writes are reported in [`StepResult::watch`].
+    pub watchpoints: Vec<u16>,
+    // which addresses have been executed since the last reset, only kept
+    // while tracking self-modifying code
+    executed: Option<Vec<bool>>,
+    hooks: Box<dyn Chip8Hooks>,
+    rng: Box<dyn RngCore>,
+    // key pressed while waiting in FX0A, stored once it's released
+    pending_key: Option<u8>,
+    // instructions' worth of time left in the current frame
+    frame_left: u32,
+    // how far the last frame ran over, taken out of the next one
+    carry: u32,
+    // whether `00FD` has run since the last reset
+    exited: bool,
+}
+
+impl Cpu {
+    /// Creates a machine with `program` loaded at 0x200. The random number
+    /// generator is seeded from `seed` if given, otherwise from entropy.
+    pub fn new(program: &[u8], quirks: Quirks, seed: Option<u64>) -> Result<Self, Chip8Error> {
+        Self::new_at(program, quirks, seed, DEFAULT_LOAD_ADDRESS)
+    }
+
+    /// Like [`Cpu::new`], but loads `program` at `load_address` and starts
+    /// running it there, for the few programs that don't start at 0x200.
+    pub fn new_at(
+        program: &[u8],
+        quirks: Quirks,
+        seed: Option<u64>,
+        load_address: u16,
+    ) -> Result<Self, Chip8Error> {
+        if load_address as usize >= quirks.memory_size() {
+            return Err(Chip8Error::InvalidLoadAddress {
+                address: load_address,
+            });
+        }
+        let max = quirks.memory_size() - load_address as usize;
+        if program.len() > max {
+            return Err(Chip8Error::RomTooLarge {
+                size: program.len(),
+                max,
+            });
+        }
+
+        let mut cpu = Self {
+            mem: Vec::new(),
+            reg: [0; 16],
+            stack: Vec::with_capacity(quirks.stack_size),
+            pc: load_address,
+            idx: 0,
+            delay: 0,
+            sound: 0,
+            pitch: DEFAULT_PITCH,
+            pattern: None,
+            screen: Display::new(),
+            quirks,
+            flags: [0; 8],
+            font: Font::default(),
+            interpreter: Vec::new(),
+            program: Vec::new(),
+            load_address,
+            watchpoints: Vec::new(),
+            executed: None,
+            hooks: Box::new(NoHooks),
+            rng: Box::new(match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            }),
+            pending_key: None,
+            frame_left: 0,
+            carry: 0,
+            exited: false,
+        };
+        cpu.reset(program);
+        Ok(cpu)
+    }
+
+    /// Replaces the font, both in memory and for later resets.
+    pub fn set_font(&mut self, font: Font) {
+        font.load_into(&mut self.mem);
+        self.font = font;
+        self.load_program();
+    }
+
+    /// Fills memory from 0x000 with `interpreter`, both now and on later
+    /// resets, standing in for the interpreter that lived below the program
+    /// on real hardware. The font is still loaded on top of it.
+    pub fn set_interpreter(&mut self, interpreter: &[u8]) -> Result<(), Chip8Error> {
+        if interpreter.len() > 0x200 {
+            return Err(Chip8Error::InterpreterTooLarge {
+                size: interpreter.len(),
+            });
+        }
+        self.mem[..interpreter.len()].copy_from_slice(interpreter);
+        self.font.load_into(&mut self.mem);
+        self.interpreter = interpreter.to_vec();
+        self.load_program();
+        Ok(())
+    }
+
+    /// Starts or stops noting which addresses are executed, so that writes to
+    /// them are reported in [`StepResult::self_modified`].
+    pub fn track_self_modification(&mut self, on: bool) {
+        self.executed = on.then(|| vec![false; self.mem.len()]);
+    }
+
+    /// Draws the numbers for `CXNN` from `rng` from now on, for embedders
+    /// that need a particular sequence rather than a seed.
+    pub fn set_rng(&mut self, rng: Box<dyn RngCore>) {
+        self.rng = rng;
+    }
+
+    /// Reports events to `hooks` from now on, including across resets.
+    pub fn set_hooks(&mut self, hooks: Box<dyn Chip8Hooks>) {
+        self.hooks = hooks;
+    }
+
+    /// Returns to the power-on state with `program` loaded at the load
+    /// address. The quirks, font, interpreter and random number generator
+    /// are kept.
+    pub fn reset(&mut self, program: &[u8]) {
+        self.mem = vec![0; self.quirks.memory_size()];
+        self.mem[..self.interpreter.len()].copy_from_slice(&self.interpreter);
+        self.font.load_into(&mut self.mem);
+        self.program = program.to_vec();
+        self.load_program();
+
+        self.reg = [0; 16];
+        self.stack.clear();
+        self.pc = self.load_address;
+        self.idx = 0;
+        self.delay = 0;
+        self.sound = 0;
+        self.pitch = DEFAULT_PITCH;
+        self.pattern = None;
+        self.screen = Display::new();
+        self.pending_key = None;
+        self.frame_left = 0;
+        self.carry = 0;
+        self.exited = false;
+        if let Some(executed) = &mut self.executed {
+            *executed = vec![false; self.mem.len()];
+        }
+    }
+
+    // the program wins over the font and interpreter if it's loaded low
+    // enough to overlap them
+    fn load_program(&mut self) {
+        let start = self.load_address as usize;
+        self.mem[start..(start + self.program.len())].copy_from_slice(&self.program);
+    }
+
+    /// A snapshot of the machine that [`Cpu::restore`] can go back to.
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            mem: self.mem.clone(),
+            reg: self.reg,
+            stack: self.stack.clone(),
+            pc: self.pc,
+            idx: self.idx,
+            delay: self.delay,
+            sound: self.sound,
+            pitch: self.pitch,
+            pattern: self.pattern,
+            screen: self.screen.clone(),
+        }
+    }
+
+    /// Restores a snapshot taken with [`Cpu::state`]. The quirks and random
+    /// number generator are left untouched.
+    pub fn restore(&mut self, state: CpuState) -> Result<(), Chip8Error> {
+        if state.mem.len() != self.mem.len() || state.stack.len() > self.quirks.stack_size {
+            return Err(Chip8Error::InvalidState);
+        }
+
+        self.mem.copy_from_slice(&state.mem);
+        self.reg = state.reg;
+        self.stack = state.stack;
+        self.pc = state.pc;
+        self.idx = state.idx;
+        self.delay = state.delay;
+        self.sound = state.sound;
+        self.pitch = state.pitch;
+        self.pattern = state.pattern;
+        self.screen = state.screen;
+        self.screen.mark_all_dirty();
+        self.pending_key = None;
+        self.exited = false;
+        Ok(())
+    }
+
+    /// The two bytes at `address` as a big-endian instruction.
+    pub fn opcode_at(&self, address: u16) -> u16 {
+        let address = address as usize;
+        u16::from_be_bytes([
+            self.mem[self.wrap(address)],
+            self.mem[self.wrap(address + 1)],
+        ])
+    }
+
+    /// Stores the result of a shift in `x` and the bit shifted out in `VF`,
+    /// in the order the shift flag quirk asks for.
+    fn set_shifted(&mut self, x: usize, result: u8, flag: u8) {
+        if self.quirks.shift_sets_flag_first {
+            self.reg[0xF] = flag;
+            self.reg[x] = result;
+        } else {
+            self.reg[x] = result;
+            self.reg[0xF] = flag;
+        }
+    }
+
+    /// Moves past the next instruction, which is twice as long if it's
+    /// XO-CHIP's `F000 NNNN`.
+    fn skip(&mut self) {
+        let len = if self.quirks.xo_chip_opcodes && self.opcode_at(self.pc) == 0xF000 {
+            4
+        } else {
+            2
+        };
+        self.pc = self.pc.wrapping_add(len);
+    }
+
+    /// Stores `value` at `address`, noting it in `result` if the address is
+    /// watched.
+    fn write(&mut self, address: usize, value: u8, result: &mut StepResult) {
+        let address = self.wrap(address);
+        if result.watch.is_none() && self.watchpoints.contains(&(address as u16)) {
+            result.watch = Some(Watch {
+                pc: self.pc.wrapping_sub(2),
+                address: address as u16,
+                old: self.mem[address],
+                new: value,
+            });
+        }
+        let executed = self.executed.as_ref().is_some_and(|e| e[address]);
+        if result.self_modified.is_none() && executed {
+            result.self_modified = Some(Watch {
+                pc: self.pc.wrapping_sub(2),
+                address: address as u16,
+                old: self.mem[address],
+                new: value,
+            });
+        }
+        self.mem[address] = value;
+    }
+
+    /// Wraps `address` around the end of memory like the address bus does.
+    fn wrap(&self, address: usize) -> usize {
+        address % self.mem.len()
+    }
+
+    /// Decrements the delay and sound timers. Returns true if the sound
+    /// timer just ran out.
+    pub fn tick_timers(&mut self) -> bool {
+        self.delay = self.delay.saturating_sub(1);
+        if self.sound > 0 {
+            self.sound -= 1;
+            if self.sound == 0 {
+                self.hooks.on_sound_stop();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// How much faster than its base rate audio plays at the current pitch,
+    /// following XO-CHIP's `4000 * 2 ^ ((pitch - 64) / 48)` Hz.
+    pub fn pitch_scale(&self) -> f32 {
+        2f32.powf((self.pitch as f32 - DEFAULT_PITCH as f32) / 48.0)
+    }
+
+    /// Starts a 60 Hz frame: ticks the timers and gives the frame `ipf`
+    /// instructions' worth of time, less however much the last frame ran
+    /// over by. Returns true if the sound timer just ran out.
+    ///
+    /// Each [`Cpu::step`] then uses up its instruction's cost, until
+    /// [`Cpu::frame_done`] says the frame is over.
+    pub fn start_frame(&mut self, ipf: u32) -> bool {
+        self.frame_left = ipf.saturating_sub(self.carry);
+        self.carry = self.carry.saturating_sub(ipf);
+        self.tick_timers()
+    }
+
+    /// Whether the frame's time is used up, or an instruction waited for
+    /// vertical blank or exited.
+    pub fn frame_done(&self) -> bool {
+        self.frame_left == 0
+    }
+
+    /// Whether the program has exited with `00FD` since the last reset or
+    /// restore. Stepping further just exits again.
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Runs one 60 Hz frame without a window: starts the frame, then executes
+    /// instructions until it's done. Returns the screen as it is at the end
+    /// of the frame.
+    pub fn step_frame(&mut self, keypad: &Keypad, ipf: u32) -> Result<&Display, Chip8Error> {
+        self.start_frame(ipf);
+        while !self.frame_done() {
+            self.step(keypad)?;
+        }
+        Ok(&self.screen)
+    }
+
+    /// Executes exactly one instruction with `keypad` as the current frame's
+    /// keys, using up its cost from the current frame. Invalid instructions
+    /// cost 1, since the program counter has still moved past them.
+    pub fn step(&mut self, keypad: &Keypad) -> Result<StepResult, Chip8Error> {
+        let result = self.execute(keypad);
+        match &result {
+            Ok(result) if result.vblank || result.exit => {
+                self.frame_left = 0;
+                self.carry = 0;
+                self.exited |= result.exit;
+            }
+            Ok(result) => self.use_time(result.cost),
+            Err(_) => self.use_time(1),
+        }
+        match result {
+            Ok(StepResult {
+                sound_toggled: true,
+                ..
+            }) => {
+                if self.sound > 0 {
+                    self.hooks.on_sound_start();
+                } else {
+                    self.hooks.on_sound_stop();
+                }
+            }
+            Ok(_) => {}
+            Err(Chip8Error::InvalidOpcode { pc, opcode }) => {
+                self.hooks.on_invalid_opcode(pc, opcode)
+            }
+            Err(Chip8Error::StackOverflow) => self.hooks.on_stack_overflow(),
+            Err(Chip8Error::StackUnderflow) => self.hooks.on_stack_underflow(),
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Takes `cost` out of the frame, carrying whatever doesn't fit over to
+    /// the next one. Outside of a frame, e.g. when single stepping, nothing
+    /// is kept.
+    fn use_time(&mut self, cost: u32) {
+        if self.frame_left > 0 {
+            self.carry = cost.saturating_sub(self.frame_left);
+            self.frame_left = self.frame_left.saturating_sub(cost);
+        }
+    }
+
+    fn execute(&mut self, keypad: &Keypad) -> Result<StepResult, Chip8Error> {
+        let mut result = StepResult {
+            cost: 1,
+            ..StepResult::default()
+        };
+
+        // running off the end of memory wraps around to the start
+        self.pc = self.wrap(self.pc as usize) as u16;
+        let opcode = self.opcode_at(self.pc);
+        let (pc, size) = (self.pc as usize, self.mem.len());
+        if let Some(executed) = &mut self.executed {
+            let len = if opcode == 0xF000 { 4 } else { 2 };
+            for i in 0..len {
+                executed[(pc + i) % size] = true;
+            }
+        }
+
+        let op = (opcode >> 12) as u8;
+        let x = ((opcode >> 8) & 0xF) as usize;
+        let y = ((opcode >> 4) & 0xF) as usize;
+        let n = (opcode & 0xF) as u8;
+        let value = (opcode & 0xFF) as u8;
+        let address = opcode & 0xFFF;
+
+        self.pc = self.pc.wrapping_add(2);
+
+        match (op, value, n) {
+            (0, 0xC0..=0xCF, _) => {
+                // scroll down n
+                self.screen.scroll_down(n as usize);
+                result.redraw = true;
+            }
+            (0, 0xE0, _) => {
+                // clear
+                self.screen.clear();
+                result.redraw = true;
+            }
+            (0, 0xEE, _) => {
+                // pop
+                self.pc = self.stack.pop().ok_or(Chip8Error::StackUnderflow)?;
+            }
+            (0, 0xFB, _) => {
+                // scroll right 4
+                self.screen.scroll_right(4);
+                result.redraw = true;
+            }
+            (0, 0xFC, _) => {
+                // scroll left 4
+                self.screen.scroll_left(4);
+                result.redraw = true;
+            }
+            (0, 0xFD, _) => {
+                // exit
+                self.pc = pc as u16;
+                result.exit = true;
+            }
+            (0, 0xFE, _) => {
+                // low resolution
+                self.screen.set_hires(false);
+                result.redraw = true;
+            }
+            (0, 0xFF, _) => {
+                // high resolution
+                self.screen.set_hires(true);
+                result.redraw = true;
+            }
+            (1, _, _) => {
+                // jump
+                self.pc = address;
+            }
+            (2, _, _) => {
+                // call subroutine
+                if self.stack.len() >= self.quirks.stack_size {
+                    return Err(Chip8Error::StackOverflow);
+                }
+                self.stack.push(self.pc);
+                self.pc = address;
+            }
+            (3, _, _) => {
+                // skip instruction if x equals value
+                if self.reg[x] == value {
+                    self.skip();
+                }
+            }
+            (4, _, _) => {
+                // skip instruction if x doesn't equals value
+                if self.reg[x] != value {
+                    self.skip();
+                }
+            }
+            (5, _, 0) => {
+                // skip instruction if x equals yj
+                if self.reg[x] == self.reg[y] {
+                    self.skip();
+                }
+            }
+            (5, _, 2) if self.quirks.xo_chip_opcodes => {
+                // store registers x to y, in either direction, starting from
+                // i without changing it
+                for (i, r) in register_range(x, y).enumerate() {
+                    self.write(self.idx as usize + i, self.reg[r], &mut result);
+                }
+            }
+            (5, _, 3) if self.quirks.xo_chip_opcodes => {
+                // load registers x to y, in either direction, starting from
+                // i without changing it
+                for (i, r) in register_range(x, y).enumerate() {
+                    self.reg[r] = self.mem[self.wrap(self.idx as usize + i)];
+                }
+            }
+            (6, _, _) => {
+                // set x to value
+                self.reg[x] = value;
+            }
+            (7, _, _) => {
+                // add value to x
+                self.reg[x] = self.reg[x].wrapping_add(value);
+            }
+            (8, _, 0) => {
+                // x = y
+                self.reg[x] = self.reg[y];
+            }
+            (8, _, 1) => {
+                // x = x OR y; flag reset
+                self.reg[x] |= self.reg[y];
+                self.reg[0xF] = 0;
+            }
+            (8, _, 2) => {
+                // x = x AND y; flag reset
+                self.reg[x] &= self.reg[y];
+                self.reg[0xF] = 0;
+            }
+            (8, _, 3) => {
+                // x = x XOR y; flag reset
+                self.reg[x] ^= self.reg[y];
+                self.reg[0xF] = 0;
+            }
+            (8, _, 4) => {
+                // x = x + y with CF; here and below the flag is written
+                // last so that VF ends up holding it when x is F
+                let (sum, carry) = self.reg[x].overflowing_add(self.reg[y]);
+                self.reg[x] = sum;
+                self.reg[0xF] = carry.into();
+            }
+            (8, _, 5) => {
+                // x = x - y with borrow
+                let (diff, borrow) = self.reg[x].overflowing_sub(self.reg[y]);
+                self.reg[x] = diff;
+                self.reg[0xF] = (!borrow).into();
+            }
+            (8, _, 6) => {
+                // x = y >> 1 with shifted bit
+                let src = if self.quirks.shift_uses_vy { y } else { x };
+                let res = self.reg[src] >> 1;
+                let flag = self.reg[src] & 1;
+                self.set_shifted(x, res, flag);
+            }
+
+            (8, _, 7) => {
+                // x = y - x with borrow
+                let (diff, borrow) = self.reg[y].overflowing_sub(self.reg[x]);
+                self.reg[x] = diff;
+                self.reg[0xF] = (!borrow).into();
+            }
+            (8, _, 0xE) => {
+                // x = y << 1 with shifted bit
+                let src = if self.quirks.shift_uses_vy { y } else { x };
+                let res = self.reg[src] << 1;
+                let flag = (self.reg[src] & (1 << 7)) >> 7;
+                self.set_shifted(x, res, flag);
+            }
+
+            (9, _, 0) => {
+                // skip instruction if x and y are not equal
+                if self.reg[x] != self.reg[y] {
+                    self.skip();
+                }
+            }
+            (0xA, _, _) => {
+                // set index
+                self.idx = address;
+            }
+            (0xB, _, _) => {
+                // jump to address + v0, or vx on SUPER-CHIP, wrapping
+                // around the end of memory
+                let base = if self.quirks.jump_offset_uses_vx {
+                    x
+                } else {
+                    0
+                };
+                let offset = self.reg[base] as u16;
+                self.pc = self.wrap((address + offset) as usize) as u16;
+            }
+            (0xC, _, _) => {
+                // x = rand() AND NN
+                self.reg[x] = self.rng.gen::<u8>() & value;
+            }
+            (0xD, _, _) => {
+                // draw
+                let width = self.screen.width();
+                let height = self.screen.height();
+                let x_pos = self.reg[x] as usize % width;
+                let y_pos = self.reg[y] as usize % height;
+
+                // DXY0 draws a 16x16 sprite in high resolution
+                let (rows, cols) = if n == 0 && self.screen.hires() {
+                    (16, 16)
+                } else {
+                    (n as usize, 8)
+                };
+                let bytes_per_row = cols / 8;
+
+                // rows that turned a pixel off, and rows clipped at the bottom
+                let mut collided = 0u8;
+                let mut clipped = 0u8;
+
+                // each selected plane reads its own sprite, one after another
+                let mut start = self.idx as usize;
+                for plane in self.screen.selected_planes().collect::<Vec<_>>() {
+                    for i in 0..rows {
+                        // pixels past the edge are either clipped or wrapped
+                        // around to the other side
+                        if y_pos + i >= height && !self.quirks.wrap_sprites {
+                            clipped += (rows - i) as u8;
+                            break;
+                        };
+                        let py = (y_pos + i) % height;
+
+                        let offset = start + i * bytes_per_row;
+                        let sprite = [self.mem[self.wrap(offset)], self.mem[self.wrap(offset + 1)]];
+
+                        let mut row_collided = false;
+                        for j in 0..cols {
+                            if x_pos + j >= width && !self.quirks.wrap_sprites {
+                                break;
+                            }
+                            let px = (x_pos + j) % width;
+
+                            if (sprite[j / 8] >> (7 - j % 8)) & 1 == 1 {
+                                row_collided |= self.screen.flip(plane, px, py);
+                            }
+                        }
+                        collided += u8::from(row_collided);
+                    }
+                    start += rows * bytes_per_row;
+                }
+                self.reg[0xf] = if self.quirks.count_collided_rows {
+                    collided + clipped
+                } else {
+                    u8::from(collided > 0)
+                };
+                self.hooks.on_draw(collided > 0);
+                result.draw = Some(Draw {
+                    pc: pc as u16,
+                    x: x_pos as u8,
+                    y: y_pos as u8,
+                    width: cols as u8,
+                    height: rows as u8,
+                    vf: self.reg[0xf],
+                });
+                if self.quirks.draw_timing {
+                    result.cost += (rows / ROWS_PER_INSTRUCTION) as u32;
+                }
+                result.redraw = true;
+                result.vblank = self.quirks.display_wait;
+            }
+            (0xE, 0x9E, _) => {
+                // skip if x is pressed
+                if keypad.held[self.reg[x] as usize] {
+                    self.skip();
+                }
+            }
+            (0xE, 0xA1, _) => {
+                // skip if x is not pressed
+                if !keypad.held[self.reg[x] as usize] {
+                    self.skip();
+                }
+            }
+            (0xF, 0x00, _) if opcode == 0xF000 && self.quirks.xo_chip_opcodes => {
+                // load the 16 bit address in the next two bytes into i
+                self.idx = self.opcode_at(self.pc);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            (0xF, 0x01, _) if self.quirks.xo_chip_opcodes => {
+                // select drawing planes
+                self.screen.set_plane_mask(x as u8);
+            }
+            (0xF, 0x02, _) if opcode == 0xF002 && self.quirks.xo_chip_opcodes => {
+                // load the audio pattern from the 16 bytes at i
+                let mut pattern = [0; 16];
+                for (i, byte) in pattern.iter_mut().enumerate() {
+                    *byte = self.mem[self.wrap(self.idx as usize + i)];
+                }
+                self.pattern = Some(pattern);
+            }
+            (0xF, 0x07, _) => {
+                // set x to delay
+                self.reg[x] = self.delay;
+            }
+            (0xF, 0x0A, _) => {
+                // wait until a key goes down and comes back up in a later
+                // frame; store key in x
+                match self.pending_key {
+                    Some(key) if keypad.released[key as usize] => {
+                        self.reg[x] = key;
+                        self.pending_key = None;
+                    }
+                    Some(_) => self.pc = self.pc.wrapping_sub(2),
+                    None => {
+                        self.pending_key = (0u8..=0xF).find(|i| keypad.pressed[*i as usize]);
+                        self.pc = self.pc.wrapping_sub(2);
+                    }
+                }
+            }
+            (0xF, 0x15, _) => {
+                // set delay to x
+                self.delay = self.reg[x];
+            }
+            (0xF, 0x18, _) => {
+                // set sound to x
+                let was_playing = self.sound > 0;
+                self.sound = self.reg[x];
+                result.sound_toggled = was_playing != (self.sound > 0);
+            }
+            (0xF, 0x1E, _) => {
+                // Add x to index
+                self.idx = self.idx.wrapping_add(self.reg[x] as u16);
+            }
+            (0xF, 0x29, _) => {
+                // Store address for font char x in i
+                self.idx = self.font.small_digit(self.reg[x]);
+            }
+            (0xF, 0x30, _) => {
+                // Store address for big font char x in i
+                self.idx = self.font.big_digit(self.reg[x]);
+            }
+            (0xF, 0x3A, _) if self.quirks.xo_chip_opcodes => {
+                // set the audio pitch to x
+                self.pitch = self.reg[x];
+            }
+            (0xF, 0x33, _) => {
+                // BCD of x into I..3
+                let number = self.reg[x];
+                let idx = self.idx as usize;
+                self.write(idx, number / 100, &mut result);
+                self.write(idx + 1, (number % 100) / 10, &mut result);
+                self.write(idx + 2, number % 10, &mut result);
+            }
+            (0xF, 0x55, _) => {
+                // Store registers till x starting from i
+                let idx = self.idx;
+                for i in 0..=x {
+                    self.write(self.idx as usize, self.reg[i], &mut result);
+                    self.idx = self.idx.wrapping_add(1);
+                }
+                if !self.quirks.load_store_increments_i {
+                    self.idx = idx;
+                }
+            }
+            (0xF, 0x65, _) => {
+                // Load registers till x starting from i
+                let idx = self.idx;
+                for i in 0..=x {
+                    self.reg[i] = self.mem[self.wrap(self.idx as usize)];
+                    self.idx = self.idx.wrapping_add(1);
+                }
+                if !self.quirks.load_store_increments_i {
+                    self.idx = idx;
+                }
+            }
+            (0xF, 0x75, _) => {
+                // save registers till x to the user flags
+                let n = x.min(7) + 1;
+                self.flags[..n].copy_from_slice(&self.reg[..n]);
+                result.flags_changed = true;
+            }
+            (0xF, 0x85, _) => {
+                // load registers till x from the user flags
+                let n = x.min(7) + 1;
+                self.reg[..n].copy_from_slice(&self.flags[..n]);
+            }
+
+            _ => {
+                return Err(Chip8Error::InvalidOpcode {
+                    pc: self.pc.wrapping_sub(2),
+                    opcode,
+                })
+            }
+        };
+
+        Ok(result)
+    }
+}
+
+/// The registers from `x` to `y` inclusive, counting down if `y` is less
+/// than `x`, as XO-CHIP's `5XY2` and `5XY3` access them.
+fn register_range(x: usize, y: usize) -> impl Iterator<Item = usize> {
+    (0..=x.abs_diff(y)).map(move |i| if x <= y { x + i } else { x - i })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asm::assemble, quirks::Platform};
+
+    fn cpu(source: &str, quirks: Quirks) -> Cpu {
+        let program = assemble(source).unwrap();
+        Cpu::new(&program, quirks, Some(0)).unwrap()
+    }
+
+    /// Steps with no keys held until the program reaches `EXIT`.
+    fn run_to_exit(cpu: &mut Cpu) {
+        for _ in 0..1000 {
+            if cpu.step(&Keypad::default()).unwrap().exit {
+                return;
+            }
+        }
+        panic!("program didn't exit");
+    }
+
+    fn run(source: &str) -> Cpu {
+        let mut cpu = cpu(source, Quirks::default());
+        run_to_exit(&mut cpu);
+        cpu
+    }
+
+    #[test]
+    fn add_into_vf_keeps_carry() {
+        let cpu = run("LD VF, 0xFF\nLD V1, 2\nADD VF, V1\nEXIT");
+        assert_eq!(cpu.reg[0xF], 1);
+        let cpu = run("LD VF, 1\nLD V1, 2\nADD VF, V1\nEXIT");
+        assert_eq!(cpu.reg[0xF], 0);
+    }
+
+    #[test]
+    fn sub_into_vf_keeps_borrow() {
+        let cpu = run("LD VF, 5\nLD V1, 3\nSUB VF, V1\nEXIT");
+        assert_eq!(cpu.reg[0xF], 1);
+        let cpu = run("LD VF, 3\nLD V1, 5\nSUB VF, V1\nEXIT");
+        assert_eq!(cpu.reg[0xF], 0);
+    }
+
+    #[test]
+    fn sub_of_equal_values_does_not_borrow() {
+        let cpu = run("LD V0, 5\nLD V1, 5\nSUB V0, V1\nEXIT");
+        assert_eq!(cpu.reg[0], 0);
+        assert_eq!(cpu.reg[0xF], 1);
+    }
+
+    #[test]
+    fn subn_into_vf_keeps_borrow() {
+        let cpu = run("LD VF, 3\nLD V1, 5\nSUBN VF, V1\nEXIT");
+        assert_eq!(cpu.reg[0xF], 1);
+        let cpu = run("LD VF, 5\nLD V1, 3\nSUBN VF, V1\nEXIT");
+        assert_eq!(cpu.reg[0xF], 0);
+    }
+
+    /// VF after running `source` with the shift quirks set as given.
+    fn shifted_vf(source: &str, shift_uses_vy: bool, shift_sets_flag_first: bool) -> u8 {
+        let quirks = Quirks {
+            shift_uses_vy,
+            shift_sets_flag_first,
+            ..Quirks::default()
+        };
+        let mut cpu = cpu(source, quirks);
+        run_to_exit(&mut cpu);
+        cpu.reg[0xF]
+    }
+
+    #[test]
+    fn shift_into_vf_keeps_flag_unless_flag_first() {
+        // 4 >> 1 is 2 with 0 shifted out
+        let shr = "LD V1, 4\nSHR VF, V1\nEXIT";
+        assert_eq!(shifted_vf(shr, true, false), 0);
+        assert_eq!(shifted_vf(shr, true, true), 2);
+
+        // 0x81 << 1 is 2 with 1 shifted out
+        let shl = "LD V1, 0x81\nSHL VF, V1\nEXIT";
+        assert_eq!(shifted_vf(shl, true, false), 1);
+        assert_eq!(shifted_vf(shl, true, true), 2);
+    }
+
+    #[test]
+    fn shift_into_vf_in_place() {
+        // without shift_uses_vy VF shifts itself and V1 is ignored
+        let shl = "LD VF, 0x81\nLD V1, 0\nSHL VF, V1\nEXIT";
+        assert_eq!(shifted_vf(shl, false, false), 1);
+        assert_eq!(shifted_vf(shl, false, true), 2);
+
+        let shr = "LD VF, 4\nLD V1, 0xFF\nSHR VF, V1\nEXIT";
+        assert_eq!(shifted_vf(shr, false, false), 0);
+        assert_eq!(shifted_vf(shr, false, true), 2);
+    }
+
+    /// VF after drawing a 3 row block at the top left, then the `n` row
+    /// block at `y` below it.
+    fn collision_vf(y: u8, n: u8, quirks: Quirks) -> u8 {
+        let source = format!(
+            "LD I, block\nLD V0, 0\nDRW V0, V0, 3\nLD V1, {y}\nDRW V0, V1, {n}\nEXIT\n\
+             block: DB 0xFF, 0xFF, 0xFF, 0xFF, 0xFF"
+        );
+        let mut cpu = cpu(&source, quirks);
+        run_to_exit(&mut cpu);
+        cpu.reg[0xF]
+    }
+
+    #[test]
+    fn collisions_count_rows_on_xo_chip() {
+        let xo_chip = Platform::XoChip.quirks();
+        assert_eq!(collision_vf(0, 3, xo_chip), 3);
+        assert_eq!(collision_vf(1, 5, xo_chip), 2);
+        assert_eq!(collision_vf(10, 3, xo_chip), 0);
+
+        for platform in [Platform::Chip8, Platform::SuperChip] {
+            assert_eq!(collision_vf(0, 3, platform.quirks()), 1);
+            assert_eq!(collision_vf(1, 5, platform.quirks()), 1);
+            assert_eq!(collision_vf(10, 3, platform.quirks()), 0);
+        }
+    }
+
+    #[test]
+    fn clipped_rows_count_as_collisions() {
+        let quirks = Quirks {
+            count_collided_rows: true,
+            ..Quirks::default()
+        };
+        // only two of the five rows fit from row 30 down, so three are clipped
+        assert_eq!(collision_vf(30, 5, quirks), 3);
+        assert_eq!(collision_vf(30, 5, Quirks::default()), 0);
+    }
+
+    fn run_xo_chip(source: &str) -> Cpu {
+        let mut cpu = cpu(source, Platform::XoChip.quirks());
+        run_to_exit(&mut cpu);
+        cpu
+    }
+
+    const REGISTERS: &str = "LD V1, 1\nLD V2, 2\nLD V3, 3\nLD I, data\n";
+
+    #[test]
+    fn save_register_range() {
+        let cpu = run_xo_chip(&format!("{REGISTERS}SAVE V1-V3\nEXIT\ndata: DB 0, 0, 0"));
+        let i = cpu.idx as usize;
+        assert_eq!(cpu.mem[i..i + 3], [1, 2, 3]);
+
+        let cpu = run_xo_chip(&format!("{REGISTERS}SAVE V3-V1\nEXIT\ndata: DB 0, 0, 0"));
+        let i = cpu.idx as usize;
+        assert_eq!(cpu.mem[i..i + 3], [3, 2, 1]);
+    }
+
+    #[test]
+    fn load_register_range() {
+        let cpu = run_xo_chip("LD I, data\nLOAD V1-V3\nEXIT\ndata: DB 7, 8, 9");
+        assert_eq!(cpu.reg[1..4], [7, 8, 9]);
+
+        let cpu = run_xo_chip("LD I, data\nLOAD V3-V1\nEXIT\ndata: DB 7, 8, 9");
+        assert_eq!(cpu.reg[1..4], [9, 8, 7]);
+    }
+
+    #[test]
+    fn register_range_is_xo_chip_only() {
+        let mut cpu = cpu("SAVE V1-V3", Quirks::default());
+        assert!(matches!(
+            cpu.step(&Keypad::default()),
+            Err(Chip8Error::InvalidOpcode { opcode: 0x5132, .. })
+        ));
+    }
+
+    #[test]
+    fn random_is_masked() {
+        let mut cpu = cpu("RND V0, 0x00\nRND V1, 0xFF\nEXIT", Quirks::default());
+        cpu.set_rng(Box::new(StdRng::seed_from_u64(7)));
+        run_to_exit(&mut cpu);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        rng.gen::<u8>();
+        assert_eq!(cpu.reg[0], 0);
+        assert_eq!(cpu.reg[1], rng.gen::<u8>());
+    }
+
+    #[test]
+    fn random_repeats_with_seed() {
+        let source = "RND V0, 0xFF\nRND V1, 0xFF\nRND V2, 0xFF\nEXIT";
+        assert_eq!(run(source).reg, run(source).reg);
+    }
+
+    #[test]
+    fn screen_hash_is_stable() {
+        let cpu = run("LD V0, 0xA\nLD F, V0\nDRW V0, V0, 5\nEXIT");
+        assert_eq!(cpu.screen.screen_hash(), 0x2B341C66FB44A23D);
+        assert_ne!(cpu.screen.screen_hash(), Display::new().screen_hash());
+    }
+
+    #[test]
+    fn frame_overrun_carries_over() {
+        let quirks = Quirks {
+            draw_timing: true,
+            ..Quirks::default()
+        };
+        // the 8 row sprite costs 3, running 1 over the first frame
+        let mut cpu = cpu("loop: DRW V0, V0, 8\nJP loop", quirks);
+        cpu.step_frame(&Keypad::default(), 2).unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        cpu.step_frame(&Keypad::default(), 2).unwrap();
+        assert_eq!(cpu.pc, 0x200);
+    }
+
+    #[test]
+    fn exit_ends_the_frame() {
+        let mut cpu = cpu("LD V0, 1\nEXIT\nLD V0, 2", Quirks::default());
+        cpu.step_frame(&Keypad::default(), 10).unwrap();
+        assert!(cpu.exited());
+        assert_eq!(cpu.reg[0], 1);
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn wait_for_key_needs_press_and_release() {
+        let mut cpu = cpu("LD V0, K\nEXIT", Quirks::default());
+        let mut keypad = Keypad::default();
+        let mut keys = [false; 16];
+        keys[5] = true;
+
+        keypad.update(keys);
+        cpu.step_frame(&keypad, 10).unwrap();
+        keypad.update(keys);
+        cpu.step_frame(&keypad, 10).unwrap();
+        assert!(!cpu.exited());
+
+        keypad.update([false; 16]);
+        cpu.step_frame(&keypad, 10).unwrap();
+        assert!(cpu.exited());
+        assert_eq!(cpu.reg[0], 5);
+    }
+
+    #[test]
+    fn load_address_past_memory_is_an_error() {
+        assert!(matches!(
+            Cpu::new_at(&[], Quirks::default(), None, 0x2000),
+            Err(Chip8Error::InvalidLoadAddress { address: 0x2000 })
+        ));
+    }
+}
@@ -0,0 +1,32 @@
+use crate::quirks::Platform;
+use serde::Deserialize;
+
+const DATABASE: &str = include_str!("roms.toml");
+
+/// What's known about a ROM from the built-in database.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RomInfo {
+    pub title: String,
+    sha1: String,
+    pub platform: Platform,
+    ipf: Option<u32>,
+}
+
+impl RomInfo {
+    /// The recommended instructions per frame.
+    pub fn ipf(&self) -> u32 {
+        self.ipf.unwrap_or(self.platform.ipf())
+    }
+}
+
+#[derive(Deserialize)]
+struct Database {
+    rom: Vec<RomInfo>,
+}
+
+/// Identifies `program` by its SHA-1 hash.
+pub fn lookup(program: &[u8]) -> Option<RomInfo> {
+    let hash = sha1_smol::Sha1::from(program).digest().to_string();
+    let database: Database = toml::from_str(DATABASE).expect("built-in ROM database is valid");
+    database.rom.into_iter().find(|rom| rom.sha1 == hash)
+}
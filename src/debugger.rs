@@ -0,0 +1,83 @@
+use std::fmt::Write;
+
+/// A command typed at the debugger prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// `s`: execute one instruction and stop again.
+    Step,
+    /// `c`: run until the next breakpoint.
+    Continue,
+    /// `b <addr>`: stop whenever `pc` reaches `addr`.
+    Break(u16),
+    /// `w <addr>`: stop after any write to `addr`.
+    Watch(u16),
+    /// `r`: dump the registers.
+    Registers,
+    /// `m <addr> <len>`: dump `len` bytes of memory starting at `addr`.
+    Memory(u16, u16),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some("s") => Self::Step,
+            Some("c") => Self::Continue,
+            Some("b") => Self::Break(parse_hex(words.next())?),
+            Some("w") => Self::Watch(parse_hex(words.next())?),
+            Some("r") => Self::Registers,
+            Some("m") => Self::Memory(parse_hex(words.next())?, parse_hex(words.next())?),
+            Some(other) => return Err(format!("unknown command {other}")),
+            None => return Err("commands: s, c, b <addr>, w <addr>, r, m <addr> <len>".to_string()),
+        };
+
+        match words.next() {
+            Some(extra) => Err(format!("unexpected argument {extra}")),
+            None => Ok(command),
+        }
+    }
+}
+
+fn parse_hex(word: Option<&str>) -> Result<u16, String> {
+    parse_address(word.ok_or("missing argument")?)
+}
+
+/// Parses a hex number with or without a `0x` prefix.
+pub fn parse_address(word: &str) -> Result<u16, String> {
+    let digits = word.trim_start_matches("0x");
+    u16::from_str_radix(digits, 16).map_err(|e| format!("{word}: {e}"))
+}
+
+/// Draws each of `bytes` as a row of an 8-pixel-wide sprite, the way `DXYN`
+/// would, labelled with its address starting at `start` and its value.
+pub fn sprite_rows(start: usize, bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, byte) in bytes.iter().enumerate() {
+        write!(out, "{:03X}: {byte:02X} ", start + i).unwrap();
+        for bit in (0..8).rev() {
+            out.push(if byte & (1 << bit) != 0 { '█' } else { '.' });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Formats `bytes` as rows of 16 hex values followed by the printable ASCII
+/// characters, labelled starting at `start`.
+pub fn hexdump(start: usize, bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (i, row) in bytes.chunks(16).enumerate() {
+        write!(out, "{:03X}:", start + 16 * i).unwrap();
+        for byte in row {
+            write!(out, " {byte:02X}").unwrap();
+        }
+        let padding = 3 * (16 - row.len());
+        write!(out, "{:padding$}  ", "").unwrap();
+        for &byte in row {
+            let printable = byte.is_ascii_graphic() || byte == b' ';
+            out.push(if printable { byte as char } else { '.' });
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,74 @@
+/// Returns the mnemonic for a single instruction, e.g. `DRW V1, V2, 5`.
+/// Opcodes that aren't instructions are shown as data words, and so is
+/// `F000`, which needs the word after it; see [`disasm_with_next`].
+pub fn disasm(opcode: u16) -> String {
+    let op = (opcode >> 12) as u8;
+    let x = (opcode >> 8) & 0xF;
+    let y = (opcode >> 4) & 0xF;
+    let n = opcode & 0xF;
+    let value = opcode & 0xFF;
+    let address = opcode & 0xFFF;
+
+    match (op, value, n) {
+        (0, 0xC0..=0xCF, _) => format!("SCD {n}"),
+        (0, 0xE0, _) => "CLS".to_string(),
+        (0, 0xEE, _) => "RET".to_string(),
+        (0, 0xFB, _) => "SCR".to_string(),
+        (0, 0xFC, _) => "SCL".to_string(),
+        (0, 0xFD, _) => "EXIT".to_string(),
+        (0, 0xFE, _) => "LOW".to_string(),
+        (0, 0xFF, _) => "HIGH".to_string(),
+        (1, _, _) => format!("JP 0x{address:03X}"),
+        (2, _, _) => format!("CALL 0x{address:03X}"),
+        (3, _, _) => format!("SE V{x:X}, 0x{value:02X}"),
+        (4, _, _) => format!("SNE V{x:X}, 0x{value:02X}"),
+        (5, _, 0) => format!("SE V{x:X}, V{y:X}"),
+        (5, _, 2) => format!("SAVE V{x:X}-V{y:X}"),
+        (5, _, 3) => format!("LOAD V{x:X}-V{y:X}"),
+        (6, _, _) => format!("LD V{x:X}, 0x{value:02X}"),
+        (7, _, _) => format!("ADD V{x:X}, 0x{value:02X}"),
+        (8, _, 0) => format!("LD V{x:X}, V{y:X}"),
+        (8, _, 1) => format!("OR V{x:X}, V{y:X}"),
+        (8, _, 2) => format!("AND V{x:X}, V{y:X}"),
+        (8, _, 3) => format!("XOR V{x:X}, V{y:X}"),
+        (8, _, 4) => format!("ADD V{x:X}, V{y:X}"),
+        (8, _, 5) => format!("SUB V{x:X}, V{y:X}"),
+        (8, _, 6) => format!("SHR V{x:X}, V{y:X}"),
+        (8, _, 7) => format!("SUBN V{x:X}, V{y:X}"),
+        (8, _, 0xE) => format!("SHL V{x:X}, V{y:X}"),
+        (9, _, 0) => format!("SNE V{x:X}, V{y:X}"),
+        (0xA, _, _) => format!("LD I, 0x{address:03X}"),
+        (0xB, _, _) => format!("JP V0, 0x{address:03X}"),
+        (0xC, _, _) => format!("RND V{x:X}, 0x{value:02X}"),
+        (0xD, _, _) => format!("DRW V{x:X}, V{y:X}, {n}"),
+        (0xE, 0x9E, _) => format!("SKP V{x:X}"),
+        (0xE, 0xA1, _) => format!("SKNP V{x:X}"),
+        (0xF, 0x01, _) => format!("PLANE {x}"),
+        (0xF, 0x02, _) if opcode == 0xF002 => "AUDIO".to_string(),
+        (0xF, 0x07, _) => format!("LD V{x:X}, DT"),
+        (0xF, 0x0A, _) => format!("LD V{x:X}, K"),
+        (0xF, 0x15, _) => format!("LD DT, V{x:X}"),
+        (0xF, 0x18, _) => format!("LD ST, V{x:X}"),
+        (0xF, 0x1E, _) => format!("ADD I, V{x:X}"),
+        (0xF, 0x29, _) => format!("LD F, V{x:X}"),
+        (0xF, 0x30, _) => format!("LD HF, V{x:X}"),
+        (0xF, 0x33, _) => format!("LD B, V{x:X}"),
+        (0xF, 0x3A, _) => format!("PITCH V{x:X}"),
+        (0xF, 0x55, _) => format!("LD [I], V{x:X}"),
+        (0xF, 0x65, _) => format!("LD V{x:X}, [I]"),
+        (0xF, 0x75, _) => format!("LD R, V{x:X}"),
+        (0xF, 0x85, _) => format!("LD V{x:X}, R"),
+        _ => format!("DW 0x{opcode:04X}"),
+    }
+}
+
+/// Like [`disasm`], but also given the word after `opcode` so that
+/// `LD I, LONG` can show its address. Returns the mnemonic and the size of
+/// the instruction in bytes.
+pub fn disasm_with_next(opcode: u16, next: u16) -> (String, usize) {
+    if opcode == 0xF000 {
+        (format!("LD I, LONG 0x{next:04X}"), 4)
+    } else {
+        (disasm(opcode), 2)
+    }
+}
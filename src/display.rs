@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+
+/// Width of the display in high resolution mode.
+pub const WIDTH: usize = 128;
+/// Height of the display in high resolution mode.
+pub const HEIGHT: usize = 64;
+/// Number of XO-CHIP drawing planes.
+pub const PLANES: usize = 2;
+
+type Plane = Vec<[u64; WIDTH / 64]>;
+
+/// A display that switches between the 64x32 CHIP-8 resolution and the
+/// 128x64 SUPER-CHIP resolution. Low resolution only uses the top left
+/// quarter of the buffer.
+///
+/// XO-CHIP adds a second plane for color. Clearing, scrolling and drawing
+/// only affect the planes selected by the plane mask.
+///
+/// Rows that change are tracked so the window only needs to redraw those.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Display {
+    planes: [Plane; PLANES],
+    plane_mask: u8,
+    hires: bool,
+    #[serde(skip, default = "all_rows")]
+    dirty: u64,
+}
+
+fn all_rows() -> u64 {
+    u64::MAX
+}
+
+impl Display {
+    /// A blank low resolution screen with only the first plane selected.
+    pub fn new() -> Self {
+        Self {
+            planes: std::array::from_fn(|_| vec![[0; WIDTH / 64]; HEIGHT]),
+            plane_mask: 1,
+            hires: false,
+            dirty: all_rows(),
+        }
+    }
+
+    /// Returns a mask of the rows that changed since the last call, with
+    /// bit `y` set if row `y` changed.
+    pub fn take_dirty(&mut self) -> u64 {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Makes the next [`Display::take_dirty`] report every row.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = all_rows();
+    }
+
+    /// Whether the screen is in SUPER-CHIP's 128x64 mode.
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switches resolution, which changes the size of every row.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.dirty = all_rows();
+    }
+
+    /// Selects the XO-CHIP planes that drawing and clearing affect.
+    pub fn set_plane_mask(&mut self, mask: u8) {
+        self.plane_mask = mask & 0b11;
+    }
+
+    /// Indices of the planes selected by the plane mask.
+    pub fn selected_planes(&self) -> impl Iterator<Item = usize> {
+        let mask = self.plane_mask;
+        (0..PLANES).filter(move |plane| mask & (1 << plane) != 0)
+    }
+
+    /// Width in pixels at the current resolution.
+    pub fn width(&self) -> usize {
+        if self.hires {
+            WIDTH
+        } else {
+            WIDTH / 2
+        }
+    }
+
+    /// Height in pixels at the current resolution.
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HEIGHT
+        } else {
+            HEIGHT / 2
+        }
+    }
+
+    /// Turns off every pixel in the selected planes.
+    pub fn clear(&mut self) {
+        for plane in self.selected_planes() {
+            self.planes[plane].fill([0; WIDTH / 64]);
+        }
+        self.dirty = all_rows();
+    }
+
+    /// Moves every row down by `n` pixels, blanking the rows at the top.
+    pub fn scroll_down(&mut self, n: usize) {
+        let height = self.height();
+        for plane in self.selected_planes() {
+            let rows = &mut self.planes[plane];
+            for y in (0..height).rev() {
+                rows[y] = if y >= n { rows[y - n] } else { [0; WIDTH / 64] };
+            }
+        }
+        self.dirty = all_rows();
+    }
+
+    /// Moves every row right by `n` pixels, blanking the leftmost columns.
+    pub fn scroll_right(&mut self, n: usize) {
+        let mask = self.width_mask();
+        for plane in self.selected_planes() {
+            for y in 0..self.height() {
+                let bits = self.row_bits(plane, y);
+                self.set_row_bits(plane, y, (bits & !mask) | ((bits & mask) >> n & mask));
+            }
+        }
+    }
+
+    /// Moves every row left by `n` pixels, blanking the rightmost columns.
+    pub fn scroll_left(&mut self, n: usize) {
+        let mask = self.width_mask();
+        for plane in self.selected_planes() {
+            for y in 0..self.height() {
+                let bits = self.row_bits(plane, y);
+                self.set_row_bits(plane, y, (bits & !mask) | ((bits & mask) << n & mask));
+            }
+        }
+    }
+
+    /// The color of a pixel, with bit 0 set from the first plane and bit 1
+    /// from the second.
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        (0..PLANES)
+            .filter(|&plane| self.lit(plane, x, y))
+            .map(|plane| 1 << plane)
+            .sum()
+    }
+
+    /// Whether the pixel at `x`, `y` is on in `plane`.
+    pub fn lit(&self, plane: usize, x: usize, y: usize) -> bool {
+        (self.row_bits(plane, y) >> (WIDTH - 1 - x)) & 1 != 0
+    }
+
+    /// Flips the pixel at `x`, `y` in `plane` and marks its row dirty.
+    /// Returns true if the pixel was on, which is a collision when drawing.
+    pub fn flip(&mut self, plane: usize, x: usize, y: usize) -> bool {
+        let was_lit = self.lit(plane, x, y);
+        let bits = self.row_bits(plane, y) ^ (1 << (WIDTH - 1 - x));
+        self.set_row_bits(plane, y, bits);
+        was_lit
+    }
+
+    /// A hash of the resolution and every pixel's color that stays the same
+    /// across runs and builds, for telling quickly whether two screens match.
+    /// This is 64-bit FNV-1a.
+    pub fn screen_hash(&self) -> u64 {
+        let mut hash = 0xcbf29ce484222325u64;
+        let mut add = |byte: u8| {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        };
+        add(self.hires as u8);
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                add(self.pixel(x, y));
+            }
+        }
+        hash
+    }
+
+    /// The screen as lines of block characters, one per pixel, for showing
+    /// in a terminal. Pixels lit in any plane are drawn.
+    pub fn render_ascii(&self) -> String {
+        let mut out = String::with_capacity((self.width() * 3 + 1) * self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                out.push(if self.pixel(x, y) != 0 { '█' } else { ' ' });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Row `y` of `plane` as one number, with the leftmost pixel in the
+    /// highest bit.
+    fn row_bits(&self, plane: usize, y: usize) -> u128 {
+        let [left, right] = self.planes[plane][y];
+        (left as u128) << 64 | right as u128
+    }
+
+    /// Replaces row `y` of `plane`, which marks it dirty.
+    fn set_row_bits(&mut self, plane: usize, y: usize, bits: u128) {
+        self.planes[plane][y] = [(bits >> 64) as u64, bits as u64];
+        self.dirty |= 1 << y;
+    }
+
+    /// The bits of a row that are on screen at the current resolution.
+    /// Low resolution only uses the left half.
+    fn width_mask(&self) -> u128 {
+        if self.hires {
+            u128::MAX
+        } else {
+            u128::MAX << 64
+        }
+    }
+}
+
+impl PartialEq for Display {
+    fn eq(&self, other: &Self) -> bool {
+        self.planes == other.planes
+            && self.plane_mask == other.plane_mask
+            && self.hires == other.hires
+    }
+}
+
+impl Eq for Display {}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scroll_down_marks_rows_dirty() {
+        let mut display = Display::new();
+        display.take_dirty();
+        display.scroll_down(4);
+        assert_ne!(display.take_dirty(), 0);
+    }
+
+    #[test]
+    fn scroll_moves_pixels_within_the_screen() {
+        let mut display = Display::new();
+        display.flip(0, 2, 0);
+        display.flip(0, 62, 0);
+        display.scroll_right(4);
+        assert!(display.lit(0, 6, 0));
+        assert!(!display.lit(0, 2, 0));
+        // pushed off the right edge of the low resolution screen
+        assert!(!display.lit(0, 63, 0) && !display.lit(0, 66, 0));
+        display.scroll_left(4);
+        assert!(display.lit(0, 2, 0));
+    }
+}
@@ -0,0 +1,77 @@
+use minifb::Key;
+
+/// Bytes shown on each row of the memory editor.
+pub const BYTES_PER_ROW: usize = 8;
+/// Rows of memory shown at once, as many as fit on the screen.
+pub const ROWS: usize = 10;
+
+/// The keys that type each hex digit, in order.
+pub const HEX_KEYS: [Key; 16] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+];
+
+/// A hex editor for the machine's memory: the byte under the cursor and the
+/// first digit typed for it, if any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEditor {
+    pub cursor: usize,
+    high: Option<u8>,
+}
+
+impl MemoryEditor {
+    pub fn new(cursor: usize) -> Self {
+        Self { cursor, high: None }
+    }
+
+    /// Moves the cursor by `delta` bytes, staying within `len` bytes of
+    /// memory, and forgets a half-typed byte.
+    pub fn move_by(&mut self, delta: isize, len: usize) {
+        self.cursor = self.cursor.saturating_add_signed(delta).min(len - 1);
+        self.high = None;
+    }
+
+    /// Takes a typed hex digit. The first one is remembered and the second
+    /// completes the byte, which is written to `mem` before the cursor moves
+    /// on to the next one.
+    pub fn type_digit(&mut self, digit: u8, mem: &mut [u8]) {
+        match self.high.take() {
+            None => self.high = Some(digit),
+            Some(high) => {
+                mem[self.cursor] = (high << 4) | digit;
+                self.move_by(1, mem.len());
+            }
+        }
+    }
+
+    /// The address of the first byte shown, keeping the cursor's row in the
+    /// middle of the screen where possible.
+    pub fn top(&self, len: usize) -> usize {
+        let last = len.div_ceil(BYTES_PER_ROW).saturating_sub(ROWS);
+        let row = (self.cursor / BYTES_PER_ROW).saturating_sub(ROWS / 2);
+        row.min(last) * BYTES_PER_ROW
+    }
+
+    /// How the byte under the cursor is shown, with the digit typed so far
+    /// in place of its value.
+    pub fn cursor_text(&self, value: u8) -> String {
+        match self.high {
+            Some(high) => format!("{high:X}_"),
+            None => format!("{value:02X}"),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::{error::Error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chip8Error {
+    StackUnderflow,
+    StackOverflow,
+    InvalidOpcode { pc: u16, opcode: u16 },
+    InvalidState,
+    RomTooLarge { size: usize, max: usize },
+    InvalidLoadAddress { address: u16 },
+    InvalidFontSize { size: usize },
+    InvalidFontAddress { address: u16 },
+    InterpreterTooLarge { size: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StackUnderflow => write!(f, "tried to pop an empty stack"),
+            Self::StackOverflow => write!(f, "too many nested subroutine calls"),
+            Self::InvalidOpcode { pc, opcode } => {
+                write!(f, "invalid instruction {opcode:04X} at {pc:03X}")
+            }
+            Self::InvalidState => write!(f, "saved state does not match this machine"),
+            Self::RomTooLarge { size, max } => {
+                write!(f, "rom is {size} bytes but only {max} fit in memory")
+            }
+            Self::InvalidLoadAddress { address } => {
+                write!(f, "load address {address:03X} is past the end of memory")
+            }
+            Self::InvalidFontSize { size } => {
+                write!(f, "font is {size} bytes, expected 80 or 240")
+            }
+            Self::InvalidFontAddress { address } => {
+                write!(f, "font at {address:03X} would overlap the program")
+            }
+            Self::InterpreterTooLarge { size } => {
+                write!(
+                    f,
+                    "interpreter is {size} bytes but only 512 fit below the program"
+                )
+            }
+        }
+    }
+}
+
+impl Error for Chip8Error {}
@@ -0,0 +1,29 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Where the RPL user flags are kept between runs: `.chip8_flags` in the
+/// home directory, or the current directory if there isn't one.
+pub fn path() -> PathBuf {
+    let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    home.join(".chip8_flags")
+}
+
+/// Reads the saved flags, which are all zero if nothing was saved yet.
+pub fn load(path: &Path) -> io::Result<[u8; 8]> {
+    let mut flags = [0; 8];
+    match fs::read(path) {
+        Ok(saved) => {
+            let n = saved.len().min(flags.len());
+            flags[..n].copy_from_slice(&saved[..n]);
+            Ok(flags)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(flags),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn save(path: &Path, flags: &[u8; 8]) -> io::Result<()> {
+    fs::write(path, flags)
+}
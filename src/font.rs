@@ -0,0 +1,120 @@
+//! The hex digit sprites `FX29` and `FX30` point `I` at. Both fonts sit
+//! together below the program, by default at:
+//!
+//! ```text
+//! 0x050-0x09F  small font, 5 bytes for each of 0 to F
+//! 0x0A0-0x13F  big font, 10 bytes for each of 0 to F
+//! ```
+
+use crate::error::Chip8Error;
+
+/// Bytes in the small font, 5 for each hex digit.
+pub const SMALL_SIZE: usize = 80;
+/// Bytes in the SUPER-CHIP big font, 10 for each hex digit.
+pub const BIG_SIZE: usize = 160;
+
+/// Where the font goes unless told otherwise.
+pub const DEFAULT_ADDRESS: u16 = 0x50;
+
+const SMALL_FONT: [u8; SMALL_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// the SUPER-CHIP only had digits 0 to 9, the letters are from Octo
+const BIG_FONT: [u8; BIG_SIZE] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// The hex digit sprites loaded into memory below the program. The big font
+/// sits right after the small one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Font {
+    small: [u8; SMALL_SIZE],
+    big: [u8; BIG_SIZE],
+    address: u16,
+}
+
+impl Font {
+    /// Builds a font from the contents of a font file at `address`. The file
+    /// holds either just the small font, or the small font followed by the
+    /// big one; a missing big font keeps the built-in one.
+    pub fn new(data: &[u8], address: u16) -> Result<Self, Chip8Error> {
+        let mut font = Self::default().at(address)?;
+        match data.len() {
+            SMALL_SIZE => font.small.copy_from_slice(data),
+            len if len == SMALL_SIZE + BIG_SIZE => {
+                font.small.copy_from_slice(&data[..SMALL_SIZE]);
+                font.big.copy_from_slice(&data[SMALL_SIZE..]);
+            }
+            size => return Err(Chip8Error::InvalidFontSize { size }),
+        }
+        Ok(font)
+    }
+
+    /// Moves the font to `address`, which has to leave room for both fonts
+    /// before the program at 0x200.
+    pub fn at(mut self, address: u16) -> Result<Self, Chip8Error> {
+        if address as usize + SMALL_SIZE + BIG_SIZE > 0x200 {
+            return Err(Chip8Error::InvalidFontAddress { address });
+        }
+        self.address = address;
+        Ok(self)
+    }
+
+    /// Address of the small sprite for `digit`.
+    pub fn small_digit(&self, digit: u8) -> u16 {
+        self.address + 5 * (digit & 0xF) as u16
+    }
+
+    /// Address of the big sprite for `digit`.
+    pub fn big_digit(&self, digit: u8) -> u16 {
+        self.address + SMALL_SIZE as u16 + 10 * (digit & 0xF) as u16
+    }
+
+    /// Copies both fonts into `mem` at the font's address.
+    pub fn load_into(&self, mem: &mut [u8]) {
+        let start = self.address as usize;
+        mem[start..start + SMALL_SIZE].copy_from_slice(&self.small);
+        mem[start + SMALL_SIZE..start + SMALL_SIZE + BIG_SIZE].copy_from_slice(&self.big);
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self {
+            small: SMALL_FONT,
+            big: BIG_FONT,
+            address: DEFAULT_ADDRESS,
+        }
+    }
+}
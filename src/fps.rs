@@ -0,0 +1,43 @@
+use std::time::{Duration, Instant};
+
+/// How often the counter reports.
+const INTERVAL: Duration = Duration::from_secs(1);
+
+/// Counts rendered frames and executed instructions to report how fast the
+/// emulator is really running.
+pub struct FpsCounter {
+    since: Instant,
+    frames: u32,
+    pub instructions: u64,
+}
+
+impl FpsCounter {
+    pub fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            frames: 0,
+            instructions: 0,
+        }
+    }
+
+    /// Counts a rendered frame. Once a second, returns the frames and
+    /// instructions per second since the last report and starts over.
+    pub fn frame(&mut self) -> Option<(f64, f64)> {
+        self.frames += 1;
+        let elapsed = self.since.elapsed();
+        if elapsed < INTERVAL {
+            return None;
+        }
+
+        let secs = elapsed.as_secs_f64();
+        let rates = (self.frames as f64 / secs, self.instructions as f64 / secs);
+        *self = Self::new();
+        Some(rates)
+    }
+}
+
+impl Default for FpsCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,112 @@
+use serde::Deserialize;
+use std::error::Error;
+
+/// Which CHIP-8 key each gamepad button presses. Buttons left out don't
+/// press anything. The face buttons are named by their position, so `south`
+/// is A on an Xbox controller.
+///
+/// ```toml
+/// [gamepad]
+/// up = 1
+/// down = 4
+/// south = 6
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamepadMap {
+    pub up: Option<u8>,
+    pub down: Option<u8>,
+    pub left: Option<u8>,
+    pub right: Option<u8>,
+    pub south: Option<u8>,
+    pub east: Option<u8>,
+    pub north: Option<u8>,
+    pub west: Option<u8>,
+    pub start: Option<u8>,
+    pub select: Option<u8>,
+}
+
+impl Default for GamepadMap {
+    /// The D-pad presses the keys under W, A, S and D on the keyboard and
+    /// the bottom face button the one under E.
+    fn default() -> Self {
+        Self {
+            up: Some(0x5),
+            down: Some(0x8),
+            left: Some(0x7),
+            right: Some(0x9),
+            south: Some(0x6),
+            east: None,
+            north: None,
+            west: None,
+            start: None,
+            select: None,
+        }
+    }
+}
+
+#[cfg(feature = "gamepad")]
+pub use connected::Gamepads;
+
+#[cfg(feature = "gamepad")]
+mod connected {
+    use super::{Error, GamepadMap};
+    use gilrs::{Button, Gilrs};
+
+    /// Every connected gamepad, read through `gilrs`.
+    pub struct Gamepads {
+        gilrs: Gilrs,
+        buttons: Vec<(Button, u8)>,
+    }
+
+    impl Gamepads {
+        pub fn new(map: GamepadMap) -> Result<Self, Box<dyn Error>> {
+            let gilrs = Gilrs::new().map_err(|e| format!("unable to read gamepads: {e}"))?;
+            let buttons = [
+                (Button::DPadUp, map.up),
+                (Button::DPadDown, map.down),
+                (Button::DPadLeft, map.left),
+                (Button::DPadRight, map.right),
+                (Button::South, map.south),
+                (Button::East, map.east),
+                (Button::North, map.north),
+                (Button::West, map.west),
+                (Button::Start, map.start),
+                (Button::Select, map.select),
+            ]
+            .into_iter()
+            .filter_map(|(button, key)| Some((button, key? & 0xF)))
+            .collect();
+            Ok(Self { gilrs, buttons })
+        }
+
+        /// The CHIP-8 keys held down on any gamepad.
+        pub fn keys(&mut self) -> [bool; 16] {
+            // events have to be drained for the button states to update
+            while self.gilrs.next_event().is_some() {}
+
+            let mut keys = [false; 16];
+            for (_, gamepad) in self.gilrs.gamepads() {
+                for &(button, key) in &self.buttons {
+                    keys[key as usize] |= gamepad.is_pressed(button);
+                }
+            }
+            keys
+        }
+    }
+}
+
+/// Stands in for the gamepads when built without the gamepad feature.
+#[cfg(not(feature = "gamepad"))]
+pub struct Gamepads;
+
+#[cfg(not(feature = "gamepad"))]
+impl Gamepads {
+    pub fn new(_map: GamepadMap) -> Result<Self, Box<dyn Error>> {
+        Ok(Self)
+    }
+
+    pub fn keys(&mut self) -> [bool; 16] {
+        [false; 16]
+    }
+}
@@ -0,0 +1,32 @@
+//! Callbacks for embedders that want to react to what the machine does
+//! without polling it after every instruction.
+
+/// Events reported by [`Cpu`](crate::cpu::Cpu) as they happen. Every method
+/// does nothing by default, so an implementation only overrides the events
+/// it cares about.
+pub trait Chip8Hooks {
+    /// A sprite was drawn. `collision` is true if it turned any pixel off.
+    fn on_draw(&mut self, _collision: bool) {}
+
+    /// The sound timer was set from zero, so the beep should start.
+    fn on_sound_start(&mut self) {}
+
+    /// The sound timer reached zero or was cleared, so the beep should stop.
+    fn on_sound_stop(&mut self) {}
+
+    /// The instruction at `pc` wasn't recognized.
+    fn on_invalid_opcode(&mut self, _pc: u16, _opcode: u16) {}
+
+    /// A subroutine call didn't fit on the stack.
+    fn on_stack_overflow(&mut self) {}
+
+    /// A return was executed with an empty stack.
+    fn on_stack_underflow(&mut self) {}
+}
+
+/// The hooks a [`Cpu`](crate::cpu::Cpu) starts with, which ignore every
+/// event.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoHooks;
+
+impl Chip8Hooks for NoHooks {}
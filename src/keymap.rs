@@ -0,0 +1,170 @@
+use minifb::Key;
+use std::{error::Error, fs, path::Path};
+
+/// The default layout maps the COSMAC VIP keypad onto the left side of a
+/// QWERTY keyboard:
+///
+/// ```text
+/// 1 2 3 C      1 2 3 4
+/// 4 5 6 D  ->  Q W E R
+/// 7 8 9 E      A S D F
+/// A 0 B F      Z X C V
+/// ```
+pub const KEY_MAPPINGS: [Key; 16] = [
+    Key::X,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Q,
+    Key::W,
+    Key::E,
+    Key::A,
+    Key::S,
+    Key::D,
+    Key::Z,
+    Key::C,
+    Key::Key4,
+    Key::R,
+    Key::F,
+    Key::V,
+];
+
+const KEY_NAMES: [(&str, Key); 106] = [
+    ("Key0", Key::Key0),
+    ("Key1", Key::Key1),
+    ("Key2", Key::Key2),
+    ("Key3", Key::Key3),
+    ("Key4", Key::Key4),
+    ("Key5", Key::Key5),
+    ("Key6", Key::Key6),
+    ("Key7", Key::Key7),
+    ("Key8", Key::Key8),
+    ("Key9", Key::Key9),
+    ("A", Key::A),
+    ("B", Key::B),
+    ("C", Key::C),
+    ("D", Key::D),
+    ("E", Key::E),
+    ("F", Key::F),
+    ("G", Key::G),
+    ("H", Key::H),
+    ("I", Key::I),
+    ("J", Key::J),
+    ("K", Key::K),
+    ("L", Key::L),
+    ("M", Key::M),
+    ("N", Key::N),
+    ("O", Key::O),
+    ("P", Key::P),
+    ("Q", Key::Q),
+    ("R", Key::R),
+    ("S", Key::S),
+    ("T", Key::T),
+    ("U", Key::U),
+    ("V", Key::V),
+    ("W", Key::W),
+    ("X", Key::X),
+    ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("F1", Key::F1),
+    ("F2", Key::F2),
+    ("F3", Key::F3),
+    ("F4", Key::F4),
+    ("F5", Key::F5),
+    ("F6", Key::F6),
+    ("F7", Key::F7),
+    ("F8", Key::F8),
+    ("F9", Key::F9),
+    ("F10", Key::F10),
+    ("F11", Key::F11),
+    ("F12", Key::F12),
+    ("F13", Key::F13),
+    ("F14", Key::F14),
+    ("F15", Key::F15),
+    ("Down", Key::Down),
+    ("Left", Key::Left),
+    ("Right", Key::Right),
+    ("Up", Key::Up),
+    ("Apostrophe", Key::Apostrophe),
+    ("Backquote", Key::Backquote),
+    ("Backslash", Key::Backslash),
+    ("Comma", Key::Comma),
+    ("Equal", Key::Equal),
+    ("LeftBracket", Key::LeftBracket),
+    ("Minus", Key::Minus),
+    ("Period", Key::Period),
+    ("RightBracket", Key::RightBracket),
+    ("Semicolon", Key::Semicolon),
+    ("Slash", Key::Slash),
+    ("Backspace", Key::Backspace),
+    ("Delete", Key::Delete),
+    ("End", Key::End),
+    ("Enter", Key::Enter),
+    ("Escape", Key::Escape),
+    ("Home", Key::Home),
+    ("Insert", Key::Insert),
+    ("Menu", Key::Menu),
+    ("PageDown", Key::PageDown),
+    ("PageUp", Key::PageUp),
+    ("Pause", Key::Pause),
+    ("Space", Key::Space),
+    ("Tab", Key::Tab),
+    ("NumLock", Key::NumLock),
+    ("CapsLock", Key::CapsLock),
+    ("ScrollLock", Key::ScrollLock),
+    ("LeftShift", Key::LeftShift),
+    ("RightShift", Key::RightShift),
+    ("LeftCtrl", Key::LeftCtrl),
+    ("RightCtrl", Key::RightCtrl),
+    ("NumPad0", Key::NumPad0),
+    ("NumPad1", Key::NumPad1),
+    ("NumPad2", Key::NumPad2),
+    ("NumPad3", Key::NumPad3),
+    ("NumPad4", Key::NumPad4),
+    ("NumPad5", Key::NumPad5),
+    ("NumPad6", Key::NumPad6),
+    ("NumPad7", Key::NumPad7),
+    ("NumPad8", Key::NumPad8),
+    ("NumPad9", Key::NumPad9),
+    ("NumPadDot", Key::NumPadDot),
+    ("NumPadSlash", Key::NumPadSlash),
+    ("NumPadAsterisk", Key::NumPadAsterisk),
+    ("NumPadMinus", Key::NumPadMinus),
+    ("NumPadPlus", Key::NumPadPlus),
+    ("NumPadEnter", Key::NumPadEnter),
+    ("LeftAlt", Key::LeftAlt),
+    ("RightAlt", Key::RightAlt),
+    ("LeftSuper", Key::LeftSuper),
+    ("RightSuper", Key::RightSuper),
+];
+
+/// Looks up a key by its `minifb` name, ignoring case. Digits can be given
+/// without the `Key` prefix.
+pub fn parse_key(name: &str) -> Option<Key> {
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| {
+            key_name.eq_ignore_ascii_case(name) || key_name.strip_prefix("Key") == Some(name)
+        })
+        .map(|(_, key)| *key)
+}
+
+/// Reads a key mapping from a file listing the keys for CHIP-8 keys 0 to F
+/// in order, separated by whitespace. Anything after a `#` is a comment.
+pub fn load(path: &Path) -> Result<[Key; 16], Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    let names: Vec<&str> = contents
+        .lines()
+        .flat_map(|line| line.split('#').next().unwrap_or("").split_whitespace())
+        .collect();
+
+    if names.len() != 16 {
+        return Err(format!("expected 16 keys in keymap, found {}", names.len()).into());
+    }
+
+    let mut mappings = KEY_MAPPINGS;
+    for (mapping, name) in mappings.iter_mut().zip(names) {
+        *mapping = parse_key(name).ok_or_else(|| format!("unknown key {name}"))?;
+    }
+    Ok(mappings)
+}
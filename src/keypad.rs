@@ -0,0 +1,22 @@
+//! The 16 key hex keypad as the machine sees it during one frame.
+
+/// Which keys are held during a frame, and which went down or came up since
+/// the frame before. The keys are read once per frame, so every instruction
+/// in the frame sees the same state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Keypad {
+    pub held: [bool; 16],
+    /// Keys held now that weren't in the previous frame.
+    pub pressed: [bool; 16],
+    /// Keys held in the previous frame that aren't now.
+    pub released: [bool; 16],
+}
+
+impl Keypad {
+    /// Moves on to the next frame, in which `keys` are held.
+    pub fn update(&mut self, keys: [bool; 16]) {
+        self.pressed = std::array::from_fn(|i| keys[i] && !self.held[i]);
+        self.released = std::array::from_fn(|i| !keys[i] && self.held[i]);
+        self.held = keys;
+    }
+}
@@ -0,0 +1,29 @@
+//! The CHIP-8 machine without any windowing or audio attached, shared by the
+//! desktop frontend and the browser build.
+//!
+//! Create a [`Cpu`] with a program and the [`Quirks`] of a [`Platform`], then
+//! call [`Cpu::step_frame`] 60 times a second with the [`Keypad`], or
+//! [`Cpu::step`] to run one instruction at a time. The screen is in
+//! [`Cpu::screen`] and the beep should play while [`Cpu::sound`] is non-zero.
+//! [`Cpu::state`] and [`Cpu::restore`] save and load the machine, and
+//! [`Cpu::set_hooks`] reports events as they happen. [`asm::assemble`] turns
+//! the mnemonics [`disasm::disasm`] prints back into a program.
+
+pub mod asm;
+pub mod cpu;
+pub mod disasm;
+pub mod display;
+pub mod error;
+pub mod font;
+pub mod hooks;
+pub mod keypad;
+pub mod quirks;
+
+pub use cpu::{Cpu, CpuState, StepResult};
+pub use error::Chip8Error;
+pub use hooks::Chip8Hooks;
+pub use keypad::Keypad;
+pub use quirks::{Platform, Quirks};
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
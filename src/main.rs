@@ -1,392 +1,1484 @@
-use bitvec::{field::BitField, order::Msb0, view::BitView};
-use cpal::{
-    traits::{DeviceTrait, HostTrait, StreamTrait},
-    Stream,
+mod audio;
+mod cli;
+mod config;
+mod database;
+mod debugger;
+mod editor;
+mod flags;
+mod fps;
+mod gamepad;
+mod keymap;
+#[cfg(feature = "octo")]
+mod octo;
+mod profile;
+mod quirk_test;
+mod recording;
+mod replay;
+mod rom;
+mod text;
+mod tui;
+
+use audio::AudioParams;
+use chip8::{cpu, disasm, display, error, font, keypad, quirks};
+use clap::Parser;
+use cli::Cli;
+use config::Config;
+use cpal::Stream;
+use cpu::{Cpu, CpuState, StepResult};
+use debugger::Command;
+use display::{Display, HEIGHT, WIDTH};
+use editor::MemoryEditor;
+use error::Chip8Error;
+use fps::FpsCounter;
+use gamepad::Gamepads;
+use image::{Rgb, RgbImage};
+use keypad::Keypad;
+use minifb::{Key, KeyRepeat, Scale, ScaleMode, Window, WindowOptions};
+use profile::Profile;
+use quirks::Platform;
+use recording::Capture;
+use replay::{InputRecorder, InputReplay};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    mem,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use minifb::{Key, Scale, Window, WindowOptions};
-use rand::{rngs::ThreadRng, thread_rng, Rng};
-use std::{env, error::Error, f32::consts::PI, fs};
-
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
-
-const KEY_MAPPINGS: [Key; 16] = [
-    Key::X,
-    Key::Key1,
-    Key::Key2,
-    Key::Key3,
-    Key::Q,
-    Key::W,
-    Key::E,
-    Key::A,
-    Key::S,
-    Key::D,
-    Key::Z,
-    Key::C,
-    Key::Key4,
-    Key::R,
-    Key::F,
-    Key::V,
-];
-
-const FONTS: [u8; 80] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
+
+const TITLE: &str = "CHIP-8";
+const IPF_STEP: u32 = 4;
+
+// F7 cycles through this many quirks, in the order of `quirk_mut`
+const QUIRK_COUNT: usize = 8;
+
+// the machine runs at 60 frames per second of wall clock time, catching up
+// on at most a few frames at once so a stall doesn't turn into a burst
+const FRAME: Duration = Duration::from_nanos(16_666_667);
+const MAX_CATCH_UP: u32 = 4;
+
+// colors for pixels set only in the second XO-CHIP plane, or in both
+const PLANE_2_COLOR: u32 = 0xAAAAAA;
+const BOTH_PLANES_COLOR: u32 = 0x555555;
+
+// the color of the lines drawn between pixels with --grid, and how many
+// window pixels wide a CHIP-8 pixel must be for them to be drawn
+const GRID_COLOR: u32 = 0x404040;
+const MIN_GRID_CELL: usize = 4;
+
+// how much a pixel that was turned off fades each frame with ghosting on
+const GHOST_DECAY: u8 = 40;
+
+// rows added below the display for the register overlay, enough for five
+// lines of text
+const HUD_HEIGHT: usize = 32;
+
+// long enough for the quirks test ROM to finish, including its display wait
+// test, which counts frames
+const QUIRK_REPORT_FRAMES: u32 = 600;
+
+// the quirks test ROM skips its menu and tests the platform stored here
+const QUIRK_TEST_PLATFORM_ADDRESS: usize = 0x1FF;
+
+struct Options {
+    config: Config,
+    // the settings before the first ROM's database entry was applied, which
+    // other ROMs in the playlist start from
+    base_config: Config,
+    seed: Option<u64>,
+    playlist: Vec<String>,
+    menu: bool,
+    debug: bool,
+    trace: Option<PathBuf>,
+    profile: bool,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    strict: bool,
+    scale: Scale,
+    grid: bool,
+    no_audio: bool,
+    watch: bool,
+    step: bool,
+    warn_smc: bool,
+    log_collisions: bool,
+    max_instructions: Option<u64>,
+    dump_screen_on_exit: Option<PathBuf>,
+}
+
 struct Emulator {
-    mem: [u8; 4096],
-    reg: [u8; 16],
-    stack: Vec<u16>,
-    pc: u16,
-    idx: u16,
-    delay: u8,
-    sound: u8,
-    screen: [u64; 32],
+    cpu: Cpu,
+    program: Vec<u8>,
     window: Window,
-    fb: [u32; WIDTH * HEIGHT],
-    stream: Stream,
-    rng: ThreadRng,
+    // the title last given to the window, so it's only set when it changes
+    window_title: String,
+    fb: Vec<u32>,
+    hud: bool,
+    fullscreen: bool,
+    scale: Scale,
+    grid: bool,
+    // the framebuffer enlarged to the window's size with the grid drawn on
+    grid_fb: Vec<u32>,
+    turbo: u32,
+    fast_forward: bool,
+    last_tick: Instant,
+    lag: Duration,
+    ghosting: bool,
+    // brightness of each window pixel, only used with ghosting
+    intensity: Vec<u8>,
+    // only kept so the audio keeps playing, `None` when playing without
+    // sound
+    _stream: Option<Stream>,
+    audio_params: Arc<Mutex<AudioParams>>,
+    ipf: u32,
+    platform: Platform,
+    base_config: Config,
+    seed: Option<u64>,
+    playlist: Vec<String>,
+    current: usize,
+    // the ROM selected in the menu while it's shown instead of the machine
+    menu: Option<usize>,
+    // the memory editor while it's shown instead of the machine
+    editor: Option<MemoryEditor>,
+    // the quirk that F8 toggles, once one has been picked with F7
+    quirk: Option<usize>,
+    // the keys held in the last frame and how they changed from the one
+    // before
+    keypad: Keypad,
+    // reload the ROM when its file changes, which it last did at `modified`
+    watch: bool,
+    modified: Option<SystemTime>,
+    state_path: PathBuf,
+    stepping: bool,
+    breakpoints: Vec<u16>,
+    fg: u32,
+    bg: u32,
+    paused: bool,
+    keymap: [Key; 16],
+    gamepads: Gamepads,
+    frame: u64,
+    recording: bool,
+    captures: Vec<Capture>,
+    trace: Option<BufWriter<File>>,
+    profile: Option<Profile>,
+    fps: Option<FpsCounter>,
+    // the last speeds reported by the FPS counter, shown in the title
+    rates: Option<String>,
+    rewind: VecDeque<CpuState>,
+    rewind_frames: usize,
+    recorder: Option<InputRecorder>,
+    replay: Option<InputReplay>,
+    strict: bool,
+    warn_smc: bool,
+    log_collisions: bool,
+    // instructions executed since the start, stopping at the maximum if
+    // there is one
+    instructions: u64,
+    max_instructions: Option<u64>,
+    dump_screen_on_exit: Option<PathBuf>,
+    skipped: u64,
+    flags_path: PathBuf,
 }
 
 impl Emulator {
-    pub fn new(program: &[u8]) -> Result<Self, Box<dyn Error>> {
-        let window = Window::new(
-            "CHIP-8",
-            WIDTH,
-            HEIGHT,
-            WindowOptions {
-                scale: Scale::X16,
-                ..Default::default()
-            },
-        )?;
-
-        let mut mem = [0; 4096];
-        mem[0x50..(0x50 + FONTS.len())].copy_from_slice(&FONTS);
-        mem[0x200..(0x200 + program.len())].copy_from_slice(program);
-
-        let device = cpal::default_host()
-            .default_output_device()
-            .ok_or("unable to get output device")?;
-        let config = device.default_output_config()?.config();
-
-        let sample_rate = config.sample_rate.0 as f32;
-        let stream = device.build_output_stream(
-            &config,
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                let mut sample_clock = 0f32;
-                for sample in data.iter_mut() {
-                    sample_clock = (sample_clock + 1.0) % sample_rate;
-                    *sample = (sample_clock * 329.0 * 2.0 * PI / sample_rate).sin();
+    pub fn new(program: &[u8], options: Options) -> Result<Self, Box<dyn Error>> {
+        let title = format!("{TITLE} - {}", rom::name(&options.playlist[0]));
+        let window = open_window(&title, HEIGHT, options.scale, false)?;
+
+        let config = options.config;
+        let audio_params = Arc::new(Mutex::new(AudioParams {
+            playing: false,
+            volume: config.volume,
+            muted: false,
+            pitch_scale: 1.0,
+            pattern: None,
+        }));
+        let stream = if options.no_audio {
+            None
+        } else {
+            match audio::build_stream(config.tone_hz, config.waveform, audio_params.clone()) {
+                Ok(stream) => Some(stream),
+                Err(e) => {
+                    eprintln!("playing without sound: {e}");
+                    None
                 }
-            },
-            |e| {
-                panic!("{e}");
-            },
-            None,
-        )?;
-        stream.pause()?;
+            }
+        };
+
+        let trace = match options.trace {
+            Some(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                Some(BufWriter::new(file))
+            }
+            None => None,
+        };
+
+        let recorder = match options.record {
+            Some(path) => Some(InputRecorder::create(&path)?),
+            None => None,
+        };
+        let replay = match options.replay {
+            Some(path) => Some(InputReplay::load(&path)?),
+            None => None,
+        };
+
+        let mut cpu = config.cpu(program, options.seed)?;
+        cpu.track_self_modification(options.warn_smc);
+        let flags_path = flags::path();
+        cpu.flags = flags::load(&flags_path)?;
 
         Ok(Self {
-            mem,
-            reg: [0; 16],
-            stack: Vec::with_capacity(12),
-            pc: 0x200,
-            idx: 0,
-            delay: 0,
-            sound: 0,
-            rng: thread_rng(),
-            screen: [0; 32],
+            cpu,
+            program: program.to_vec(),
             window,
-            stream,
-            fb: [0; WIDTH * HEIGHT],
+            window_title: title,
+            _stream: stream,
+            audio_params,
+            fb: vec![0; WIDTH * HEIGHT],
+            hud: false,
+            fullscreen: false,
+            scale: options.scale,
+            grid: options.grid,
+            grid_fb: Vec::new(),
+            turbo: config.turbo,
+            fast_forward: false,
+            last_tick: Instant::now(),
+            lag: Duration::ZERO,
+            ghosting: config.ghosting,
+            intensity: vec![0; WIDTH * HEIGHT],
+            ipf: config.ipf(),
+            platform: config.platform(),
+            base_config: options.base_config,
+            seed: options.seed,
+            state_path: rom::state_path(&options.playlist[0]),
+            playlist: options.playlist,
+            current: 0,
+            menu: options.menu.then_some(0),
+            editor: None,
+            quirk: None,
+            keypad: Keypad::default(),
+            watch: options.watch,
+            modified: rom::modified(&options.playlist[0]),
+            stepping: options.debug,
+            breakpoints: Vec::new(),
+            fg: config.fg,
+            bg: config.bg,
+            paused: options.step,
+            keymap: config.keymap,
+            gamepads: Gamepads::new(config.gamepad)?,
+            frame: 0,
+            recording: false,
+            captures: Vec::new(),
+            trace,
+            profile: options.profile.then(Profile::new),
+            fps: None,
+            rates: None,
+            rewind: VecDeque::with_capacity(config.rewind_frames),
+            rewind_frames: config.rewind_frames,
+            recorder,
+            replay,
+            strict: options.strict,
+            warn_smc: options.warn_smc,
+            log_collisions: options.log_collisions,
+            instructions: 0,
+            max_instructions: options.max_instructions,
+            dump_screen_on_exit: options.dump_screen_on_exit,
+            skipped: 0,
+            flags_path,
         })
     }
 
-    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
-        self.window.set_target_fps(60);
+    /// Reads debugger commands from stdin until told to step or continue.
+    fn prompt(&mut self) -> Result<(), Box<dyn Error>> {
+        self.blit_and_update()?;
+
+        println!("{}", current_instruction(&self.cpu));
+
+        loop {
+            print!("> ");
+            io::stdout().flush()?;
 
-        while self.window.is_open() && !self.window.is_key_down(Key::Escape) {
-            self.delay = self.delay.saturating_sub(1);
-            if self.sound > 0 {
-                self.sound -= 1;
-                if self.sound == 0 {
-                    self.stream.pause()?;
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line)? == 0 {
+                // stdin closed, so there's nobody left to debug with
+                self.stepping = false;
+                self.breakpoints.clear();
+                return Ok(());
+            }
+
+            match Command::parse(&line) {
+                Ok(Command::Step) => {
+                    self.stepping = true;
+                    return Ok(());
+                }
+                Ok(Command::Continue) => {
+                    self.stepping = false;
+                    return Ok(());
                 }
+                Ok(Command::Break(address)) => {
+                    if !self.breakpoints.contains(&address) {
+                        self.breakpoints.push(address);
+                    }
+                }
+                Ok(Command::Watch(address)) => {
+                    if !self.cpu.watchpoints.contains(&address) {
+                        self.cpu.watchpoints.push(address);
+                    }
+                }
+                Ok(Command::Registers) => {
+                    let cpu = &self.cpu;
+                    println!(
+                        "PC={:03X} I={:03X} DT={:02X} ST={:02X}",
+                        cpu.pc, cpu.idx, cpu.delay, cpu.sound
+                    );
+                    for (i, value) in cpu.reg.iter().enumerate() {
+                        print!("V{i:X}={value:02X} ");
+                    }
+                    println!();
+                    println!("stack: {:03X?}", cpu.stack);
+                }
+                Ok(Command::Memory(address, len)) => {
+                    let start = (address as usize).min(self.cpu.mem.len());
+                    let end = (start + len as usize).min(self.cpu.mem.len());
+                    print!("{}", debugger::hexdump(start, &self.cpu.mem[start..end]));
+                }
+                Err(e) => eprintln!("{e}"),
             }
+        }
+    }
 
-            let key_pressed = self.window.get_keys();
-            let key_released = self.window.get_keys_released();
+    pub fn save_state(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let state = bincode::serialize(&self.cpu.state())?;
+        fs::write(path, state)?;
+        Ok(())
+    }
 
-            let mut redraw = false;
+    pub fn load_state(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let state = bincode::deserialize(&fs::read(path)?)?;
+        self.cpu.restore(state)?;
 
-            // 700 op/s = ~12 op/frame
-            for _cycles in 0..12 {
-                let bits = self.mem[self.pc as usize..(self.pc + 2) as usize].view_bits::<Msb0>();
+        self.set_beep(self.cpu.sound > 0);
+        self.blit_and_update()
+    }
 
-                let op = bits[0..4].load_be::<u8>();
-                let x = bits[4..8].load_be::<usize>();
-                let y = bits[8..12].load_be::<usize>();
-                let n = bits[12..].load_be::<u8>();
-                let value = bits[8..].load_be::<u8>();
-                let address = bits[4..].load_be::<u16>();
+    /// The keys held down on the keyboard or any gamepad.
+    fn keys(&mut self) -> [bool; 16] {
+        let pad = self.gamepads.keys();
+        let mut keys = self.keymap.map(|key| self.window.is_key_down(key));
+        for (key, pressed) in keys.iter_mut().zip(pad) {
+            *key |= pressed;
+        }
+        keys
+    }
 
-                self.pc += 2;
+    /// The keypad for this frame, taken from the replay if there is one and
+    /// written to the input recording if there is one.
+    fn frame_keys(&mut self) -> Result<Keypad, Box<dyn Error>> {
+        let keys = match self.replay.as_mut().map(InputReplay::next_frame) {
+            Some(Some(keys)) => keys,
+            Some(None) => {
+                println!(
+                    "replay finished, screen hash {:016X}",
+                    self.cpu.screen.screen_hash()
+                );
+                self.replay = None;
+                self.keys()
+            }
+            None => self.keys(),
+        };
 
-                match (op, value, n) {
-                    (0, 0xE0, _) => {
-                        // clear
-                        self.screen.fill(0);
-                        redraw = true;
-                    }
-                    (0, 0xEE, _) => {
-                        // pop
-                        self.pc = self.stack.pop().ok_or("tried to pop an empty stack")?;
-                    }
-                    (1, _, _) => {
-                        // jump
-                        self.pc = address;
-                    }
-                    (2, _, _) => {
-                        // call subroutine
-                        self.stack.push(self.pc);
-                        self.pc = address;
-                    }
-                    (3, _, _) => {
-                        // skip instruction if x equals value
-                        if self.reg[x] == value {
-                            self.pc += 2;
-                        }
-                    }
-                    (4, _, _) => {
-                        // skip instruction if x doesn't equals value
-                        if self.reg[x] != value {
-                            self.pc += 2;
-                        }
-                    }
-                    (5, _, 0) => {
-                        // skip instruction if x equals yj
-                        if self.reg[x] == self.reg[y] {
-                            self.pc += 2;
-                        }
-                    }
-                    (6, _, _) => {
-                        // set x to value
-                        self.reg[x] = value;
-                    }
-                    (7, _, _) => {
-                        // add value to x
-                        self.reg[x] = self.reg[x].wrapping_add(value);
-                    }
-                    (8, _, 0) => {
-                        // x = y
-                        self.reg[x] = self.reg[y];
-                    }
-                    (8, _, 1) => {
-                        // x = x OR y; flag reset
-                        self.reg[x] |= self.reg[y];
-                        self.reg[0xF] = 0;
-                    }
-                    (8, _, 2) => {
-                        // x = x AND y; flag reset
-                        self.reg[x] &= self.reg[y];
-                        self.reg[0xF] = 0;
-                    }
-                    (8, _, 3) => {
-                        // x = x XOR y; flag reset
-                        self.reg[x] ^= self.reg[y];
-                        self.reg[0xF] = 0;
-                    }
-                    (8, _, 4) => {
-                        // x = x + y with CF
-                        let (sum, overflow) = self.reg[x].overflowing_add(self.reg[y]);
-                        self.reg[x] = sum;
-                        self.reg[0xF] = overflow.into();
-                    }
-                    (8, _, 5) => {
-                        // x = x - y with borrow
-                        let (diff, overflow) = self.reg[x].overflowing_sub(self.reg[y]);
-                        self.reg[x] = diff;
-                        self.reg[0xF] = (!overflow).into();
-                    }
-                    (8, _, 6) => {
-                        // x = y >> 1 with shifted bit
-                        let res = self.reg[y] >> 1;
-                        let flag = self.reg[y] & 1;
-                        self.reg[x] = res;
-                        self.reg[0xF] = flag;
-                    }
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&keys)?;
+        }
+        self.keypad.update(keys);
+        Ok(self.keypad)
+    }
 
-                    (8, _, 7) => {
-                        // x = y - x with borrow
-                        let (value, overflow) = self.reg[y].overflowing_sub(self.reg[x]);
-                        self.reg[x] = value;
-                        self.reg[0xF] = (!overflow).into();
-                    }
-                    (8, _, 0xE) => {
-                        // x = y << 1 with shifted bit
-                        let res = self.reg[y] << 1;
-                        let flag = (self.reg[y] & (1 << 7)) >> 7;
-                        self.reg[x] = res;
-                        self.reg[0xF] = flag;
-                    }
+    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        self.window.set_target_fps(60);
 
-                    (9, _, 0) => {
-                        // skip instruction if x and y are not equal
-                        if self.reg[x] != self.reg[y] {
-                            self.pc += 2;
-                        }
-                    }
-                    (0xA, _, _) => {
-                        // set index
-                        self.idx = address;
-                    }
-                    (0xB, _, _) => {
-                        // jump to address + v0
-                        let offset = self.reg[0] as u16;
-                        self.pc = address + offset
-                    }
-                    (0xC, _, _) => {
-                        // x = rand() AND NN
-                        self.reg[x] = self.rng.gen::<u8>() & value;
-                    }
-                    (0xD, _, _) => {
-                        // draw
-                        let x_pos = (self.reg[x] % 64) as usize;
-                        let y_pos = (self.reg[y] % 32) as usize;
-
-                        self.reg[0xf] = 0;
-
-                        for i in 0..n as usize {
-                            if y_pos + i >= 32 {
-                                break;
-                            };
-
-                            let b = self.mem[self.idx as usize + i].view_bits::<Msb0>();
-                            let row = self.screen[y_pos + i].view_bits_mut::<Msb0>();
-
-                            for j in 0..8 {
-                                if x_pos + j >= 64 {
-                                    break;
-                                }
-
-                                if b[j] {
-                                    if row[x_pos + j] {
-                                        self.reg[0xf] = 1;
-                                        row.set(x_pos + j, false); // true xor true = false
-                                    } else {
-                                        row.set(x_pos + j, true); // true xor false = true
-                                    }
-                                }
-                            }
-                        }
-                        redraw = true;
-                    }
-                    (0xE, 0x9E, _) => {
-                        // skip if x is pressed
-                        if key_pressed.contains(&KEY_MAPPINGS[self.reg[x] as usize]) {
-                            self.pc += 2;
-                        }
-                    }
-                    (0xE, 0xA1, _) => {
-                        // skip if x is not pressed
-                        if !key_pressed.contains(&KEY_MAPPINGS[self.reg[x] as usize]) {
-                            self.pc += 2;
-                        }
-                    }
-                    (0xF, 0x07, _) => {
-                        // set x to delay
-                        self.reg[x] = self.delay;
-                    }
-                    (0xF, 0x0A, _) => {
-                        // wait until key; store key in x
-                        if let Some(i) =
-                            (0u8..0xF).find(|i| key_released.contains(&KEY_MAPPINGS[*i as usize]))
-                        {
-                            self.reg[x] = i;
-                        } else {
-                            self.pc -= 2;
-                        }
-                    }
-                    (0xF, 0x15, _) => {
-                        // set delay to x
-                        self.delay = self.reg[x];
-                    }
-                    (0xF, 0x18, _) => {
-                        // set sound to x
-                        self.sound = self.reg[x];
-                        if self.sound == 0 {
-                            self.stream.pause()?;
-                        } else {
-                            self.stream.play()?;
-                        }
-                    }
-                    (0xF, 0x1E, _) => {
-                        // Add x to index
-                        self.idx = self.idx.wrapping_add(self.reg[x] as u16);
-                    }
-                    (0xF, 0x29, _) => {
-                        // Store address for font char x in i
-                        self.idx = 0x50 + 5 * self.reg[(x & 0xF) as usize] as u16;
-                    }
-                    (0xF, 0x33, _) => {
-                        // BCD of x into I..3
-                        let number = self.reg[x];
-                        self.mem[self.idx as usize] = number / 100;
-                        self.mem[self.idx as usize + 1] = (number % 100) / 10;
-                        self.mem[self.idx as usize + 2] = number % 10;
-                    }
-                    (0xF, 0x55, _) => {
-                        // Store registers till x starting from i
-                        for i in 0..=x {
-                            self.mem[self.idx as usize] = self.reg[i];
-                            self.idx += 1;
-                        }
-                    }
-                    (0xF, 0x65, _) => {
-                        // Load registers till x starting from i
-                        for i in 0..=x {
-                            self.reg[i] = self.mem[self.idx as usize];
-                            self.idx += 1;
-                        }
+        while self.window.is_open()
+            && !self.window.is_key_down(Key::Escape)
+            && !self.cpu.exited()
+            && !self.out_of_instructions()
+        {
+            self.frame += 1;
+
+            if let Some(selected) = self.menu {
+                self.last_tick = Instant::now();
+                self.menu_frame(selected)?;
+                continue;
+            }
+            if let Some(editor) = self.editor {
+                self.last_tick = Instant::now();
+                self.editor_frame(editor)?;
+                continue;
+            }
+            if self.window.is_key_pressed(Key::F6, KeyRepeat::No) {
+                let cursor = (self.cpu.idx as usize).min(self.cpu.mem.len() - 1);
+                self.editor = Some(MemoryEditor::new(cursor));
+                self.set_beep(false);
+                continue;
+            }
+
+            if self.window.is_key_pressed(Key::P, KeyRepeat::No) {
+                self.paused = !self.paused;
+                self.set_beep(!self.paused && self.cpu.sound > 0);
+            }
+
+            if self.window.is_key_pressed(Key::F12, KeyRepeat::No) {
+                let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+                let path = PathBuf::from(format!("screenshot-{millis}.png"));
+                match self.screenshot(&path) {
+                    Ok(()) => println!("saved screenshot to {}", path.display()),
+                    Err(e) => eprintln!("unable to save screenshot: {e}"),
+                }
+            }
+
+            if self.window.is_key_pressed(Key::F11, KeyRepeat::No) {
+                self.toggle_recording();
+            }
+
+            if self.window.is_key_pressed(Key::F3, KeyRepeat::No) {
+                self.fps = match self.fps {
+                    Some(_) => {
+                        self.rates = None;
+                        None
                     }
+                    None => Some(FpsCounter::new()),
+                };
+            }
+            if let Some((fps, ips)) = self.fps.as_mut().and_then(FpsCounter::frame) {
+                self.rates = Some(format!("{fps:.0} fps, {ips:.0} ips, {} ipf", self.ipf));
+            }
+
+            if self.window.is_key_pressed(Key::F2, KeyRepeat::No) {
+                self.hud = !self.hud;
+                self.reopen_window()?;
+            }
 
-                    _ => return Err("invalid instruction".into()),
+            let alt =
+                self.window.is_key_down(Key::LeftAlt) || self.window.is_key_down(Key::RightAlt);
+            if alt && self.window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+                self.fullscreen = !self.fullscreen;
+                self.reopen_window()?;
+            }
+
+            if self.playlist.len() > 1 {
+                let next = if self.window.is_key_pressed(Key::N, KeyRepeat::No) {
+                    Some(self.current + 1)
+                } else if self.window.is_key_pressed(Key::B, KeyRepeat::No) {
+                    Some(self.current + self.playlist.len() - 1)
+                } else {
+                    None
                 };
+                if let Some(next) = next {
+                    let next = next % self.playlist.len();
+                    if let Err(e) = self.switch_rom(next) {
+                        eprintln!("unable to load {}: {e}", self.playlist[next]);
+                    }
+                }
+
+                if self.window.is_key_pressed(Key::F4, KeyRepeat::No) {
+                    self.menu = Some(self.current);
+                    self.set_beep(false);
+                    continue;
+                }
             }
 
-            if redraw {
-                for (y, row) in self.screen.iter().enumerate() {
-                    for (x, col) in row.view_bits::<Msb0>().iter().enumerate() {
-                        self.fb[y * WIDTH + x] = if *col { 0xFFFFFFFF } else { 0 };
+            if self.watch {
+                let modified = rom::modified(&self.playlist[self.current]);
+                if modified != self.modified {
+                    let source = self.playlist[self.current].clone();
+                    match self.switch_rom(self.current) {
+                        Ok(()) => println!("reloaded {source}"),
+                        Err(e) => eprintln!("unable to reload {source}: {e}"),
                     }
+                    // a file that's still being written is retried once it
+                    // changes again
+                    self.modified = modified;
                 }
-                self.window.update_with_buffer(&self.fb, WIDTH, HEIGHT)?;
+            }
+
+            if self.window.is_key_pressed(Key::F1, KeyRepeat::No) {
+                self.cpu.reset(&self.program);
+                self.set_beep(false);
+                self.blit_and_update()?;
+            }
+
+            self.fast_forward = self.window.is_key_down(Key::Tab);
+            self.update_title();
+
+            if self.paused {
+                self.last_tick = Instant::now();
+                if self.window.is_key_pressed(Key::Space, KeyRepeat::Yes) {
+                    println!("{}", current_instruction(&self.cpu));
+                    let keys = self.keys();
+                    self.keypad.update(keys);
+                    let keypad = self.keypad;
+                    self.execute(&keypad)?;
+                    self.blit_and_update()?;
+                } else {
+                    self.window.update();
+                }
+                continue;
+            }
+
+            if self.window.is_key_down(Key::Backspace) {
+                self.last_tick = Instant::now();
+                self.step_back()?;
+                continue;
+            }
+
+            if self.window.is_key_pressed(Key::F7, KeyRepeat::No) {
+                let index = self.quirk.map_or(0, |i| (i + 1) % QUIRK_COUNT);
+                self.quirk = Some(index);
+                let (name, quirk) = self.quirk_mut(index);
+                println!("selected {name}: {quirk}");
+            }
+            if self.window.is_key_pressed(Key::F8, KeyRepeat::No) {
+                if let Some(index) = self.quirk {
+                    let (name, quirk) = self.quirk_mut(index);
+                    *quirk = !*quirk;
+                    println!("{name}: {quirk}");
+                }
+            }
+
+            if self.window.is_key_pressed(Key::Equal, KeyRepeat::Yes) {
+                self.ipf += IPF_STEP;
+                println!("ipf: {}", self.ipf);
+            }
+            if self.window.is_key_pressed(Key::Minus, KeyRepeat::Yes) {
+                self.ipf = self.ipf.saturating_sub(IPF_STEP).max(1);
+                println!("ipf: {}", self.ipf);
+            }
+
+            if self.window.is_key_pressed(Key::M, KeyRepeat::No) {
+                let mut params = self.audio_params.lock().unwrap();
+                params.muted = !params.muted;
+                println!("muted: {}", params.muted);
+            }
+            if self.window.is_key_pressed(Key::LeftBracket, KeyRepeat::Yes) {
+                let mut params = self.audio_params.lock().unwrap();
+                params.volume = (params.volume - audio::VOLUME_STEP).max(0.0);
+                println!("volume: {:.1}", params.volume);
+            }
+            if self
+                .window
+                .is_key_pressed(Key::RightBracket, KeyRepeat::Yes)
+            {
+                let mut params = self.audio_params.lock().unwrap();
+                params.volume = (params.volume + audio::VOLUME_STEP).min(1.0);
+                println!("volume: {:.1}", params.volume);
+            }
+
+            if self.window.is_key_pressed(Key::F5, KeyRepeat::No) {
+                let path = self.state_path.clone();
+                match self.save_state(&path) {
+                    Ok(()) => println!("saved state to {}", path.display()),
+                    Err(e) => eprintln!("unable to save state: {e}"),
+                }
+            }
+            if self.window.is_key_pressed(Key::F9, KeyRepeat::No) {
+                let path = self.state_path.clone();
+                match self.load_state(&path) {
+                    Ok(()) => println!("loaded state from {}", path.display()),
+                    Err(e) => eprintln!("unable to load state: {e}"),
+                }
+            }
+
+            let mut frames = self.frames_due();
+            if self.fast_forward {
+                frames *= self.turbo.max(1);
+            }
+            let mut redraw = false;
+            for _ in 0..frames {
+                let keypad = self.frame_keys()?;
+                redraw |= self.emulate_frame(&keypad)?;
+            }
+
+            if redraw || self.hud || self.ghosting {
+                self.blit_and_update()?;
             } else {
                 self.window.update();
             }
         }
 
+        if self.cpu.exited() || self.out_of_instructions() {
+            print_stop(&self.cpu, self.instructions);
+        }
+        if let Some(path) = &self.dump_screen_on_exit {
+            dump_screen(&self.cpu.screen, self.palette(), path);
+        }
+        if let Some(profile) = &self.profile {
+            print!("{}", profile.summary());
+        }
+        if self.skipped > 0 {
+            println!("skipped {} invalid instructions", self.skipped);
+        }
+        Ok(())
+    }
+
+    /// The name of one of the boolean quirks of the running machine, and the
+    /// quirk itself.
+    fn quirk_mut(&mut self, index: usize) -> (&'static str, &mut bool) {
+        let quirks = &mut self.cpu.quirks;
+        match index {
+            0 => (
+                "load_store_increments_i",
+                &mut quirks.load_store_increments_i,
+            ),
+            1 => ("shift_uses_vy", &mut quirks.shift_uses_vy),
+            2 => ("display_wait", &mut quirks.display_wait),
+            3 => ("jump_offset_uses_vx", &mut quirks.jump_offset_uses_vx),
+            4 => ("wrap_sprites", &mut quirks.wrap_sprites),
+            5 => ("count_collided_rows", &mut quirks.count_collided_rows),
+            6 => ("draw_timing", &mut quirks.draw_timing),
+            _ => ("shift_sets_flag_first", &mut quirks.shift_sets_flag_first),
+        }
+    }
+
+    /// How many 60 Hz frames have passed since the last call, so that the
+    /// timers and the program keep time even if the window doesn't.
+    fn frames_due(&mut self) -> u32 {
+        let now = Instant::now();
+        self.lag += now - self.last_tick;
+        self.last_tick = now;
+
+        let due = (self.lag.as_nanos() / FRAME.as_nanos()) as u32;
+        self.lag -= FRAME * due;
+        due.min(MAX_CATCH_UP)
+    }
+
+    /// Remembers the state for rewinding, then runs one 60 Hz frame of the
+    /// machine: `ipf` instructions, or fewer if one waits for vertical blank,
+    /// which uses up the rest of the frame, or takes longer with the draw
+    /// timing quirk. Returns true if the screen needs to be redrawn.
+    fn emulate_frame(&mut self, keypad: &Keypad) -> Result<bool, Box<dyn Error>> {
+        self.snapshot();
+        if self.cpu.start_frame(self.ipf) {
+            self.set_beep(false);
+        }
+
+        let mut redraw = false;
+
+        while !self.cpu.frame_done() {
+            if self.out_of_instructions() {
+                break;
+            }
+            if self.stepping || self.breakpoints.contains(&self.cpu.pc) {
+                self.prompt()?;
+            }
+
+            redraw |= self.execute(keypad)?.redraw;
+        }
+
+        let mut params = self.audio_params.lock().unwrap();
+        params.pitch_scale = self.cpu.pitch_scale();
+        params.pattern = self.cpu.pattern;
+        Ok(redraw)
+    }
+
+    fn out_of_instructions(&self) -> bool {
+        self.max_instructions
+            .is_some_and(|max| self.instructions >= max)
+    }
+
+    /// Executes one instruction, tracing and profiling it, and reacts to
+    /// what it did other than drawing.
+    fn execute(&mut self, keypad: &Keypad) -> Result<StepResult, Box<dyn Error>> {
+        if let Some(trace) = &mut self.trace {
+            writeln!(trace, "{}", current_instruction(&self.cpu))?;
+        }
+        if let Some(profile) = &mut self.profile {
+            profile.record(self.cpu.opcode_at(self.cpu.pc));
+        }
+        if let Some(fps) = &mut self.fps {
+            fps.instructions += 1;
+        }
+        self.instructions += 1;
+
+        let result = match self.cpu.step(keypad) {
+            Ok(result) => result,
+            // the program counter has already moved past it
+            Err(e @ Chip8Error::InvalidOpcode { .. }) if !self.strict => {
+                eprintln!("skipping {e}");
+                self.skipped += 1;
+                StepResult::default()
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if result.sound_toggled {
+            self.set_beep(self.cpu.sound > 0 && !self.paused);
+        }
+
+        if let Some(watch) = result.watch {
+            println!(
+                "{:03X} written by {:03X}: {:02X} -> {:02X}",
+                watch.address, watch.pc, watch.old, watch.new
+            );
+            self.stepping = true;
+        }
+        if let Some(write) = result.self_modified {
+            eprintln!(
+                "{:03X} overwrote executed code at {:03X}: {:02X} -> {:02X}",
+                write.pc, write.address, write.old, write.new
+            );
+        }
+
+        if let Some(draw) = result.draw.filter(|_| self.log_collisions) {
+            let collision = if draw.vf > 0 {
+                "collision"
+            } else {
+                "no collision"
+            };
+            eprintln!(
+                "{:03X} drew {}x{} at {},{}: {collision}, VF = {}",
+                draw.pc, draw.width, draw.height, draw.x, draw.y, draw.vf
+            );
+        }
+
+        if result.flags_changed {
+            if let Err(e) = flags::save(&self.flags_path, &self.cpu.flags) {
+                eprintln!("unable to save flags: {e}");
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Starts or stops the beep, which the audio callback fades in or out.
+    fn set_beep(&self, on: bool) {
+        self.audio_params.lock().unwrap().playing = on;
+    }
+
+    /// Remembers the current state for rewinding, forgetting the oldest one
+    /// once the buffer is full.
+    fn snapshot(&mut self) {
+        if self.rewind_frames == 0 {
+            return;
+        }
+        if self.rewind.len() == self.rewind_frames {
+            self.rewind.pop_front();
+        }
+        self.rewind.push_back(self.cpu.state());
+    }
+
+    /// Goes back to the state from the previous frame, if there is one.
+    fn step_back(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(state) = self.rewind.pop_back() {
+            self.cpu.restore(state)?;
+            self.set_beep(self.cpu.sound > 0);
+        }
+        self.blit_and_update()
+    }
+
+    fn palette(&self) -> [u32; 4] {
+        [self.bg, self.fg, PLANE_2_COLOR, BOTH_PLANES_COLOR]
+    }
+
+    /// Saves the screen as a PNG at its native resolution.
+    pub fn screenshot(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        save_screen(&self.cpu.screen, self.palette(), path)
+    }
+
+    fn toggle_recording(&mut self) {
+        self.recording = !self.recording;
+        if self.recording {
+            println!("recording started");
+            self.captures
+                .push(Capture::new(self.frame, &self.cpu.screen));
+            return;
+        }
+
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = PathBuf::from(format!("recording-{millis}.gif"));
+        let captures = std::mem::take(&mut self.captures);
+        match recording::save_gif(&path, &captures, self.palette(), self.frame) {
+            Ok(()) => println!("saved recording to {}", path.display()),
+            Err(e) => eprintln!("unable to save recording: {e}"),
+        }
+    }
+
+    /// The window title, naming the ROM that's running and its platform,
+    /// whether it's paused or fast forwarding, and the speed if F3 turned
+    /// the counter on.
+    fn title(&self) -> String {
+        let mut title = format!(
+            "{TITLE} - {} [{}]",
+            rom::name(&self.playlist[self.current]),
+            self.platform
+        );
+        if self.paused {
+            title.push_str(" (PAUSED)");
+        } else if self.fast_forward {
+            title.push_str(" (TURBO)");
+        }
+        if let Some(rates) = &self.rates {
+            title.push_str(" - ");
+            title.push_str(rates);
+        }
+        title
+    }
+
+    fn update_title(&mut self) {
+        let title = self.title();
+        if title != self.window_title {
+            self.window.set_title(&title);
+            self.window_title = title;
+        }
+    }
+
+    /// Replaces the running ROM with the one at `index` in the playlist,
+    /// starting over from power-on with the settings for that ROM. The RPL
+    /// flags and watchpoints carry over, but the rewind history doesn't.
+    fn switch_rom(&mut self, index: usize) -> Result<(), Box<dyn Error>> {
+        let source = &self.playlist[index];
+        let program = rom::load(source)?;
+        let mut config = self.base_config.clone();
+        if let Some(rom) = database::lookup(&program) {
+            println!("detected {} for {}", rom.title, rom.platform);
+            config.apply_rom_info(&rom);
+        }
+
+        let mut cpu = config.cpu(&program, self.seed)?;
+        cpu.flags = self.cpu.flags;
+        cpu.watchpoints = mem::take(&mut self.cpu.watchpoints);
+        cpu.track_self_modification(self.warn_smc);
+        self.cpu = cpu;
+        self.program = program;
+        self.ipf = config.ipf();
+        self.platform = config.platform();
+        self.state_path = rom::state_path(source);
+        self.current = index;
+        self.modified = rom::modified(source);
+        self.rewind.clear();
+        self.set_beep(false);
+
+        self.update_title();
+        self.blit_and_update()
+    }
+
+    /// Lets the user pick a ROM from the playlist with the arrow keys and
+    /// `Enter`, then starts it.
+    fn menu_frame(&mut self, mut selected: usize) -> Result<(), Box<dyn Error>> {
+        if self.window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+            selected = selected.saturating_sub(1);
+        }
+        if self.window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+            selected = (selected + 1).min(self.playlist.len() - 1);
+        }
+        self.menu = Some(selected);
+
+        if self.window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            match self.switch_rom(selected) {
+                Ok(()) => {
+                    self.menu = None;
+                    return Ok(());
+                }
+                Err(e) => eprintln!("unable to load {}: {e}", self.playlist[selected]),
+            }
+        }
+
+        self.draw_menu(selected);
+        self.window
+            .update_with_buffer(&self.fb, WIDTH, self.window_height())?;
+        Ok(())
+    }
+
+    /// Lets the user move around memory with the arrow keys and Page Up and
+    /// Down and overwrite bytes by typing hex digits. `F6` goes back to the
+    /// machine.
+    fn editor_frame(&mut self, mut editor: MemoryEditor) -> Result<(), Box<dyn Error>> {
+        if self.window.is_key_pressed(Key::F6, KeyRepeat::No) {
+            self.editor = None;
+            self.set_beep(self.cpu.sound > 0 && !self.paused);
+            self.cpu.screen.mark_all_dirty();
+            return self.blit_and_update();
+        }
+
+        let len = self.cpu.mem.len();
+        let row = editor::BYTES_PER_ROW as isize;
+        let page = (editor::ROWS * editor::BYTES_PER_ROW) as isize;
+        let moves = [
+            (Key::Left, -1),
+            (Key::Right, 1),
+            (Key::Up, -row),
+            (Key::Down, row),
+            (Key::PageUp, -page),
+            (Key::PageDown, page),
+        ];
+        for (key, delta) in moves {
+            if self.window.is_key_pressed(key, KeyRepeat::Yes) {
+                editor.move_by(delta, len);
+            }
+        }
+        for (digit, key) in editor::HEX_KEYS.into_iter().enumerate() {
+            if self.window.is_key_pressed(key, KeyRepeat::No) {
+                editor.type_digit(digit as u8, &mut self.cpu.mem);
+            }
+        }
+        self.editor = Some(editor);
+
+        self.draw_editor(editor);
+        self.window
+            .update_with_buffer(&self.fb, WIDTH, self.window_height())?;
+        Ok(())
+    }
+
+    /// Shows a page of memory as rows of hex bytes, with the byte under the
+    /// cursor in inverted colors.
+    fn draw_editor(&mut self, editor: MemoryEditor) {
+        self.fb[..WIDTH * HEIGHT].fill(self.bg);
+
+        let char_width = text::GLYPH_WIDTH + 1;
+        let line_height = text::GLYPH_HEIGHT + 1;
+        let mem = &self.cpu.mem;
+        let top = editor.top(mem.len());
+        for row in 0..editor::ROWS {
+            let start = top + row * editor::BYTES_PER_ROW;
+            let end = (start + editor::BYTES_PER_ROW).min(mem.len());
+            let y = 1 + row * line_height;
+            let label = format!("{start:04X}");
+            text::draw_text(&mut self.fb, WIDTH, 1, y, &label, self.fg);
+
+            for (address, &value) in (start..end).zip(&mem[start..end]) {
+                let x = 1 + (5 + 3 * (address - start)) * char_width;
+                if address != editor.cursor {
+                    let byte = format!("{value:02X}");
+                    text::draw_text(&mut self.fb, WIDTH, x, y, &byte, self.fg);
+                    continue;
+                }
+
+                // a block of the foreground color with the byte cut out of it
+                for py in y - 1..y + text::GLYPH_HEIGHT {
+                    let row = py * WIDTH;
+                    self.fb[row + x - 1..row + x + 2 * char_width].fill(self.fg);
+                }
+                let byte = editor.cursor_text(value);
+                text::draw_text(&mut self.fb, WIDTH, x, y, &byte, self.bg);
+            }
+        }
+    }
+
+    /// Lists as many ROMs as fit on the screen, scrolled to keep `selected`
+    /// in view.
+    fn draw_menu(&mut self, selected: usize) {
+        self.fb.fill(self.bg);
+
+        let line_height = text::GLYPH_HEIGHT + 1;
+        let rows = self.window_height() / line_height;
+        let top = selected
+            .saturating_sub(rows / 2)
+            .min(self.playlist.len().saturating_sub(rows));
+        for (row, source) in self.playlist.iter().enumerate().skip(top).take(rows) {
+            let marker = if row == selected { '>' } else { ' ' };
+            text::draw_text(
+                &mut self.fb,
+                WIDTH,
+                1,
+                1 + (row - top) * line_height,
+                &format!("{marker} {}", rom::name(source)),
+                self.fg,
+            );
+        }
+    }
+
+    /// Opens the window again after the overlay or fullscreen was toggled,
+    /// with room for the overlay below the display if it's shown.
+    fn reopen_window(&mut self) -> Result<(), Box<dyn Error>> {
+        let height = self.window_height();
+        self.window = open_window(&self.window_title, height, self.scale, self.fullscreen)?;
+        self.window.set_target_fps(60);
+        self.fb = vec![0; WIDTH * height];
+        self.cpu.screen.mark_all_dirty();
+        self.blit_and_update()
+    }
+
+    fn window_height(&self) -> usize {
+        if self.hud {
+            HEIGHT + HUD_HEIGHT
+        } else {
+            HEIGHT
+        }
+    }
+
+    /// How many times larger than the framebuffer the grid is drawn, which
+    /// is the window's scale so the lines are one pixel wide, or `None` if
+    /// there's no grid or CHIP-8 pixels are too small for one.
+    fn grid_factor(&self) -> Option<usize> {
+        if !self.grid {
+            return None;
+        }
+        let factor = match self.scale {
+            Scale::X1 if !self.fullscreen => 1,
+            Scale::X2 if !self.fullscreen => 2,
+            Scale::X4 if !self.fullscreen => 4,
+            _ => 8,
+        };
+        let cell = factor * WIDTH / self.cpu.screen.width();
+        (cell >= MIN_GRID_CELL).then_some(factor)
+    }
+
+    /// Enlarges the framebuffer `factor` times into `grid_fb` and draws a
+    /// line along the top and left edge of every CHIP-8 pixel, leaving the
+    /// overlay alone.
+    fn draw_grid(&mut self, factor: usize) {
+        let width = WIDTH * factor;
+        let cell = factor * WIDTH / self.cpu.screen.width();
+        self.grid_fb
+            .resize(width * self.window_height() * factor, 0);
+        for (y, row) in self.grid_fb.chunks_mut(width).enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let line = y < HEIGHT * factor && (x % cell == 0 || y % cell == 0);
+                *pixel = if line {
+                    GRID_COLOR
+                } else {
+                    self.fb[y / factor * WIDTH + x / factor]
+                };
+            }
+        }
+    }
+
+    /// Draws the registers, timers and stack depth below the display.
+    fn draw_hud(&mut self) {
+        let hud = &mut self.fb[WIDTH * HEIGHT..];
+        hud.fill(self.bg);
+
+        let cpu = &self.cpu;
+        let mut lines = vec![format!(
+            "PC {:03X} I {:03X} SP {:X} DT {:02X} ST {:02X}",
+            cpu.pc,
+            cpu.idx,
+            cpu.stack.len(),
+            cpu.delay,
+            cpu.sound
+        )];
+        for (row, values) in cpu.reg.chunks(4).enumerate() {
+            let regs: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| format!("V{:X} {value:02X}", row * 4 + i))
+                .collect();
+            lines.push(regs.join(" "));
+        }
+
+        for (i, line) in lines.iter().enumerate() {
+            text::draw_text(
+                hud,
+                WIDTH,
+                1,
+                1 + i * (text::GLYPH_HEIGHT + 1),
+                line,
+                self.fg,
+            );
+        }
+    }
+
+    fn blit_and_update(&mut self) -> Result<(), Box<dyn Error>> {
+        // low resolution pixels are doubled to fill the window
+        let dirty = self.cpu.screen.take_dirty();
+        if dirty == 0 && !self.hud && !self.ghosting {
+            self.window.update();
+            return Ok(());
+        }
+
+        let palette = self.palette();
+        let scale = WIDTH / self.cpu.screen.width();
+        for y in 0..HEIGHT {
+            // fading pixels change every frame, not just when drawn to
+            if dirty & (1 << (y / scale)) == 0 && !self.ghosting {
+                continue;
+            }
+            for x in 0..WIDTH {
+                let i = y * WIDTH + x;
+                let color = self.cpu.screen.pixel(x / scale, y / scale);
+                self.fb[i] = if !self.ghosting || color != 0 {
+                    self.intensity[i] = u8::MAX;
+                    palette[color as usize]
+                } else {
+                    self.intensity[i] = self.intensity[i].saturating_sub(GHOST_DECAY);
+                    blend(self.bg, self.fg, self.intensity[i])
+                };
+            }
+        }
+        if self.hud {
+            self.draw_hud();
+        }
+        match self.grid_factor() {
+            Some(factor) => {
+                self.draw_grid(factor);
+                let height = self.window_height() * factor;
+                self.window
+                    .update_with_buffer(&self.grid_fb, WIDTH * factor, height)?;
+            }
+            None => {
+                self.window
+                    .update_with_buffer(&self.fb, WIDTH, self.window_height())?;
+            }
+        }
+
+        if self.recording && dirty != 0 {
+            if self.captures.len() < recording::MAX_FRAMES {
+                self.captures
+                    .push(Capture::new(self.frame, &self.cpu.screen));
+            } else {
+                eprintln!("recording is full");
+                self.toggle_recording();
+            }
+        }
         Ok(())
     }
 }
+
+/// Mixes two colors, going from `from` at 0 to `to` at 255.
+fn blend(from: u32, to: u32, amount: u8) -> u32 {
+    let [_, r1, g1, b1] = from.to_be_bytes();
+    let [_, r2, g2, b2] = to.to_be_bytes();
+    let mix = |a: u8, b: u8| {
+        let (a, b, t) = (a as u32, b as u32, amount as u32);
+        (a * (255 - t) + b * t) / 255
+    };
+    (mix(r1, r2) << 16) | (mix(g1, g2) << 8) | mix(b1, b2)
+}
+
+/// Opens a resizable window at `scale` that letterboxes the screen to keep
+/// its aspect ratio. Fullscreen is approximated by a borderless window scaled
+/// to fit the screen, since minifb can't switch video modes.
+fn open_window(
+    title: &str,
+    height: usize,
+    scale: Scale,
+    fullscreen: bool,
+) -> Result<Window, Box<dyn Error>> {
+    let window = Window::new(
+        title,
+        WIDTH,
+        height,
+        WindowOptions {
+            scale: if fullscreen { Scale::FitScreen } else { scale },
+            scale_mode: ScaleMode::AspectRatioStretch,
+            resize: !fullscreen,
+            borderless: fullscreen,
+            ..Default::default()
+        },
+    )?;
+    Ok(window)
+}
+
+fn disassemble(program: &[u8], load_address: u16) {
+    let mut i = 0;
+    while i < program.len() {
+        let address = load_address as usize + i;
+        match program[i..] {
+            [0xF0, 0x00, hi, lo, ..] => {
+                let next = u16::from_be_bytes([hi, lo]);
+                let (text, size) = disasm::disasm_with_next(0xF000, next);
+                println!("{address:03X}  F000 {next:04X}  {text}");
+                i += size;
+            }
+            [hi, lo, ..] => {
+                let opcode = u16::from_be_bytes([hi, lo]);
+                println!("{address:03X}  {opcode:04X}  {}", disasm::disasm(opcode));
+                i += 2;
+            }
+            [byte] => {
+                println!("{address:03X}  {byte:02X}    DB 0x{byte:02X}");
+                i += 1;
+            }
+            [] => unreachable!(),
+        }
+    }
+}
+
+/// The instruction at the program counter as its address, opcode and
+/// mnemonic, with the address after `F000` for `LD I, LONG`.
+fn current_instruction(cpu: &Cpu) -> String {
+    let pc = cpu.pc;
+    let opcode = cpu.opcode_at(pc);
+    let next = cpu.opcode_at(pc.wrapping_add(2));
+    match disasm::disasm_with_next(opcode, next) {
+        (text, 4) => format!("{pc:03X}  {opcode:04X} {next:04X}  {text}"),
+        (text, _) => format!("{pc:03X}  {opcode:04X}  {text}"),
+    }
+}
+
+/// Saves `screen` as a PNG at its native resolution, with a color from
+/// `palette` for each pixel value.
+fn save_screen(screen: &Display, palette: [u32; 4], path: &Path) -> Result<(), Box<dyn Error>> {
+    let image = RgbImage::from_fn(screen.width() as u32, screen.height() as u32, |x, y| {
+        let color = palette[screen.pixel(x as usize, y as usize) as usize];
+        let [_, r, g, b] = color.to_be_bytes();
+        Rgb([r, g, b])
+    });
+    image.save(path)?;
+    Ok(())
+}
+
+/// Saves `screen` for `--dump-screen-on-exit`, or prints it as text if the
+/// path is `-`.
+fn dump_screen(screen: &Display, palette: [u32; 4], path: &Path) {
+    if path.as_os_str() == "-" {
+        print!("{}", screen.render_ascii());
+        return;
+    }
+    match save_screen(screen, palette, path) {
+        Ok(()) => println!("saved screen to {}", path.display()),
+        Err(e) => eprintln!("unable to save screen: {e}"),
+    }
+}
+
+/// Prints why the program stopped, where, and a hash of its screen.
+fn print_stop(cpu: &Cpu, instructions: u64) {
+    let reason = if cpu.exited() { "exited" } else { "stopped" };
+    println!(
+        "{reason} after {instructions} instructions at {:03X}, screen hash {:016X}",
+        cpu.pc,
+        cpu.screen.screen_hash()
+    );
+}
+
+/// Runs `cpu` without a window or sound, as fast as it goes and with no keys
+/// held, until the program exits or has executed `max_instructions`.
+/// Invalid instructions are skipped like in the window unless `strict`.
+fn headless(
+    mut cpu: Cpu,
+    ipf: u32,
+    max_instructions: Option<u64>,
+    strict: bool,
+    dump_screen_on_exit: Option<&Path>,
+    palette: [u32; 4],
+) -> Result<(), Box<dyn Error>> {
+    let keypad = Keypad::default();
+    let mut instructions = 0;
+    let mut skipped = 0;
+
+    let done = |cpu: &Cpu, instructions: u64| {
+        cpu.exited() || max_instructions.is_some_and(|max| instructions >= max)
+    };
+    'frames: while !done(&cpu, instructions) {
+        cpu.start_frame(ipf);
+        while !cpu.frame_done() {
+            if done(&cpu, instructions) {
+                break 'frames;
+            }
+            match cpu.step(&keypad) {
+                Ok(_) => {}
+                // the program counter has already moved past it
+                Err(e @ Chip8Error::InvalidOpcode { .. }) if !strict => {
+                    eprintln!("skipping {e}");
+                    skipped += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+            instructions += 1;
+        }
+    }
+
+    print_stop(&cpu, instructions);
+    if let Some(path) = dump_screen_on_exit {
+        dump_screen(&cpu.screen, palette, path);
+    }
+    if skipped > 0 {
+        println!("skipped {skipped} invalid instructions");
+    }
+    Ok(())
+}
+
+/// Runs the quirks test ROM loaded in `cpu` without a window for the
+/// platform its quirks came from, then prints the quirks and the screen,
+/// which the ROM fills with a pass or fail for each category.
+fn quirk_report(mut cpu: Cpu, platform: Platform, ipf: u32) -> Result<(), Box<dyn Error>> {
+    cpu.mem[QUIRK_TEST_PLATFORM_ADDRESS] = match platform {
+        Platform::Chip8 | Platform::CosmacVip => 1,
+        Platform::SuperChip => 2,
+        Platform::XoChip => 3,
+    };
+
+    let keypad = Keypad::default();
+    for _ in 0..QUIRK_REPORT_FRAMES {
+        cpu.step_frame(&keypad, ipf)?;
+        if cpu.exited() {
+            break;
+        }
+    }
+
+    println!("{platform} quirks: {:#?}", cpu.quirks);
+    match quirk_test::results(&cpu.screen) {
+        Some(results) => {
+            for (category, result) in quirk_test::CATEGORIES.iter().zip(results) {
+                let result = match result {
+                    Some(true) => "pass",
+                    Some(false) => "fail",
+                    None => "unknown",
+                };
+                println!("{category:<12}  {result}");
+            }
+        }
+        // not the quirks test, or it didn't get to its results
+        None => print!("{}", cpu.screen.render_ascii()),
+    }
+    Ok(())
+}
+
+/// Runs `count` instructions without a window or audio and reports the
+/// interpreter's throughput.
+fn bench(mut cpu: Cpu, count: u64) -> Result<(), Box<dyn Error>> {
+    let keypad = Keypad::default();
+
+    let start = Instant::now();
+    for _ in 0..count {
+        cpu.step(&keypad)?;
+    }
+    let elapsed = start.elapsed();
+
+    println!(
+        "{count} instructions in {elapsed:?} ({:.0} instructions/s)",
+        count as f64 / elapsed.as_secs_f64()
+    );
+    println!("screen hash {:016X}", cpu.screen.screen_hash());
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let path = env::args().skip(1).next().ok_or("rom path not provided")?;
-    let f = fs::read(path)?;
-    let mut emu = Emulator::new(&f)?;
+    let cli = Cli::parse();
+
+    // options given on the command line override the config file
+    let mut config = match &cli.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    if let Some(fg) = cli.fg {
+        config.fg = fg;
+    }
+    if let Some(bg) = cli.bg {
+        config.bg = bg;
+    }
+    if let Some(platform) = cli.platform {
+        config.platform = Some(platform);
+    }
+    if let Some(ipf) = cli.ipf {
+        config.ipf = Some(ipf);
+    }
+    config.quirks = cli.quirks.or(config.quirks);
+    if cli.ghosting {
+        config.ghosting = true;
+    }
+    if let Some(path) = &cli.keymap {
+        config.keymap = keymap::load(path)?;
+    }
+    if let Some(tone) = cli.tone {
+        config.tone_hz = tone;
+    }
+    if let Some(wave) = cli.wave {
+        config.waveform = wave;
+    }
+    if let Some(path) = cli.font {
+        config.font = Some(path);
+    }
+    if let Some(path) = cli.load_interpreter {
+        config.interpreter = Some(path);
+    }
+    if let Some(address) = cli.load_addr {
+        config.load_address = address;
+    }
+    if let Some(frames) = cli.rewind {
+        config.rewind_frames = frames;
+    }
+
+    let (playlist, menu) = rom::expand(cli.roms)?;
+    let mut f = rom::load(&playlist[0])?;
+    // look the ROM up before patching, which would change its hash
+    let known = database::lookup(&f);
+    for patch in cli.patch.chunks(2) {
+        let address = debugger::parse_address(&patch[0])?;
+        rom::patch(&mut f, config.load_address, address, &patch[1])?;
+    }
+
+    if cli.disassemble {
+        disassemble(&f, config.load_address);
+        return Ok(());
+    }
+
+    let base_config = config.clone();
+    if let Some(rom) = known {
+        println!("detected {} for {}", rom.title, rom.platform);
+        config.apply_rom_info(&rom);
+    }
+
+    if let Some(range) = cli.dump_mem {
+        let cpu = config.cpu(&f, cli.seed)?;
+        let start = (range[0] as usize).min(cpu.mem.len());
+        let end = (start + range[1] as usize).min(cpu.mem.len());
+        print!("{}", debugger::hexdump(start, &cpu.mem[start..end]));
+        return Ok(());
+    }
+
+    if let Some(range) = cli.view_sprites {
+        let cpu = config.cpu(&f, cli.seed)?;
+        let start = (range[0] as usize).min(cpu.mem.len());
+        let end = (start + range[1] as usize).min(cpu.mem.len());
+        print!("{}", debugger::sprite_rows(start, &cpu.mem[start..end]));
+        return Ok(());
+    }
+
+    if let Some(count) = cli.bench {
+        return bench(config.cpu(&f, cli.seed)?, count);
+    }
+
+    if cli.tui {
+        return tui::run(config.cpu(&f, cli.seed)?, config.ipf());
+    }
+
+    if cli.quirk_report {
+        return quirk_report(config.cpu(&f, cli.seed)?, config.platform(), config.ipf());
+    }
+
+    if cli.headless {
+        let palette = [config.bg, config.fg, PLANE_2_COLOR, BOTH_PLANES_COLOR];
+        return headless(
+            config.cpu(&f, cli.seed)?,
+            config.ipf(),
+            cli.max_instructions,
+            cli.strict,
+            cli.dump_screen_on_exit.as_deref(),
+            palette,
+        );
+    }
+
+    if cli.record.is_some() && cli.seed.is_none() {
+        eprintln!("recording input without --seed, so random numbers won't replay exactly");
+    }
+
+    let options = Options {
+        config,
+        base_config,
+        seed: cli.seed,
+        playlist,
+        menu,
+        debug: cli.debug,
+        trace: cli.trace,
+        profile: cli.profile,
+        record: cli.record,
+        replay: cli.replay,
+        strict: cli.strict,
+        scale: cli.scale,
+        grid: cli.grid,
+        no_audio: cli.no_audio,
+        watch: cli.watch,
+        step: cli.step,
+        warn_smc: cli.warn_smc,
+        log_collisions: cli.log_collisions,
+        max_instructions: cli.max_instructions,
+        dump_screen_on_exit: cli.dump_screen_on_exit,
+    };
+    let mut emu = Emulator::new(&f, options)?;
     emu.run()?;
     Ok(())
 }
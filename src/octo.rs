@@ -0,0 +1,584 @@
+//! A small assembler for the common subset of Octo's language: labels,
+//! constants, register aliases, the instructions of all three platforms,
+//! `if ... then`, `if ... begin ... else ... end` and `loop ... again` with
+//! `while`. Macros and `:calc` aren't supported.
+
+use chip8::cpu::DEFAULT_LOAD_ADDRESS;
+use std::collections::HashMap;
+
+/// Assembles Octo `source` into a program to load at 0x200. Execution
+/// starts at the `main` label, so a jump to it is put first unless it's
+/// already the first thing in the program.
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let tokens = tokenize(source);
+    let mut asm = Assembler {
+        tokens,
+        pos: 0,
+        out: Vec::new(),
+        labels: HashMap::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: Vec::new(),
+        loops: Vec::new(),
+        branches: Vec::new(),
+    };
+
+    let starts_with_main = matches!(asm.tokens.get(..2), Some([(":", _), ("main", _)]));
+    if !starts_with_main {
+        asm.fixups.push(Fixup {
+            at: 0,
+            label: "main",
+            line: 1,
+            long: false,
+        });
+        asm.emit(0x1000);
+    }
+
+    while asm.pos < asm.tokens.len() {
+        asm.statement()?;
+    }
+    if let Some(open) = asm.loops.first() {
+        return Err(format!("line {}: loop without again", open.line));
+    }
+    if let Some(&(_, line)) = asm.branches.first() {
+        return Err(format!("line {line}: begin without end"));
+    }
+    asm.resolve()?;
+    Ok(asm.out)
+}
+
+/// Splits `source` into words with their line numbers, dropping comments.
+fn tokenize(source: &str) -> Vec<(&str, usize)> {
+    source
+        .lines()
+        .enumerate()
+        .flat_map(|(i, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            code.split_whitespace().map(move |token| (token, i + 1))
+        })
+        .collect()
+}
+
+/// A label used before it was defined, filled in once all are known.
+struct Fixup<'a> {
+    at: usize,
+    label: &'a str,
+    line: usize,
+    // a 16 bit address after `F000` instead of the low 12 bits of an opcode
+    long: bool,
+}
+
+/// A `loop` waiting for its `again`.
+struct Loop {
+    start: u16,
+    // the jumps out of the loop made by `while`
+    breaks: Vec<usize>,
+    line: usize,
+}
+
+/// What an `if` or `while` tests.
+enum Condition {
+    Equal(u8, Operand),
+    NotEqual(u8, Operand),
+    Key(u8),
+    NotKey(u8),
+}
+
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+impl Condition {
+    fn negate(self) -> Self {
+        match self {
+            Self::Equal(x, rhs) => Self::NotEqual(x, rhs),
+            Self::NotEqual(x, rhs) => Self::Equal(x, rhs),
+            Self::Key(x) => Self::NotKey(x),
+            Self::NotKey(x) => Self::Key(x),
+        }
+    }
+
+    /// The instruction that skips the next one unless the condition holds.
+    fn skip_unless(&self) -> u16 {
+        let reg = |x: &u8| (*x as u16) << 8;
+        match self {
+            Self::Equal(x, Operand::Byte(n)) => 0x4000 | reg(x) | *n as u16,
+            Self::Equal(x, Operand::Register(y)) => 0x9000 | reg(x) | ((*y as u16) << 4),
+            Self::NotEqual(x, Operand::Byte(n)) => 0x3000 | reg(x) | *n as u16,
+            Self::NotEqual(x, Operand::Register(y)) => 0x5000 | reg(x) | ((*y as u16) << 4),
+            Self::Key(x) => 0xE0A1 | reg(x),
+            Self::NotKey(x) => 0xE09E | reg(x),
+        }
+    }
+}
+
+struct Assembler<'a> {
+    tokens: Vec<(&'a str, usize)>,
+    pos: usize,
+    out: Vec<u8>,
+    labels: HashMap<&'a str, u16>,
+    constants: HashMap<&'a str, u16>,
+    aliases: HashMap<&'a str, u8>,
+    fixups: Vec<Fixup<'a>>,
+    loops: Vec<Loop>,
+    // the jump placeholder of each open `begin` or `else`, with its line
+    branches: Vec<(usize, usize)>,
+}
+
+impl<'a> Assembler<'a> {
+    fn statement(&mut self) -> Result<(), String> {
+        let (token, line) = self.next()?;
+        let at = |e: String| format!("line {line}: {e}");
+        self.instruction(token, line).map_err(at)
+    }
+
+    fn instruction(&mut self, token: &'a str, line: usize) -> Result<(), String> {
+        match token {
+            ":" => {
+                let name = self.name()?;
+                if self.labels.insert(name, self.here()).is_some() {
+                    return Err(format!("label {name} is defined twice"));
+                }
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":byte" => {
+                let value = self.byte()?;
+                self.out.push(value);
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "scroll-down" => {
+                let n = self.value()? & 0xF;
+                self.emit(0x00C0 | n);
+            }
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "exit" => self.emit(0x00FD),
+            "lores" => self.emit(0x00FE),
+            "hires" => self.emit(0x00FF),
+            "jump" => self.address(0x1000)?,
+            "jump0" => self.address(0xB000)?,
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.value()? & 0xF;
+                self.emit(0xD000 | (x << 8) | (y << 4) | n);
+            }
+            "bcd" => self.register_op(0xF033)?,
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                let range = if self.peek() == Some("-") {
+                    self.pos += 1;
+                    Some(self.register()? as u16)
+                } else {
+                    None
+                };
+                let opcode = match (token, range) {
+                    ("save", None) => 0xF055 | (x << 8),
+                    (_, None) => 0xF065 | (x << 8),
+                    ("save", Some(y)) => 0x5002 | (x << 8) | (y << 4),
+                    (_, Some(y)) => 0x5003 | (x << 8) | (y << 4),
+                };
+                self.emit(opcode);
+            }
+            "saveflags" => self.register_op(0xF075)?,
+            "loadflags" => self.register_op(0xF085)?,
+            "plane" => {
+                let n = self.value()? & 0xF;
+                self.emit(0xF001 | (n << 8));
+            }
+            "audio" => self.emit(0xF002),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let opcode = match token {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.register_op(opcode)?;
+            }
+            "i" => self.index()?,
+            "if" => {
+                let condition = self.condition()?;
+                match self.next()?.0 {
+                    "then" => self.emit(condition.skip_unless()),
+                    "begin" => {
+                        self.emit(condition.negate().skip_unless());
+                        self.branches.push((self.out.len(), line));
+                        self.emit(0x1000);
+                    }
+                    other => return Err(format!("expected then or begin, found {other}")),
+                }
+            }
+            "else" => {
+                let (jump, _) = self.branches.pop().ok_or("else without if")?;
+                self.branches.push((self.out.len(), line));
+                self.emit(0x1000);
+                self.patch(jump, self.here());
+            }
+            "end" => {
+                let (jump, _) = self.branches.pop().ok_or("end without if")?;
+                self.patch(jump, self.here());
+            }
+            "loop" => self.loops.push(Loop {
+                start: self.here(),
+                breaks: Vec::new(),
+                line,
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                self.emit(condition.negate().skip_unless());
+                let at = self.out.len();
+                self.emit(0x1000);
+                let open = self.loops.last_mut().ok_or("while outside a loop")?;
+                open.breaks.push(at);
+            }
+            "again" => {
+                let open = self.loops.pop().ok_or("again without loop")?;
+                self.emit(0x1000 | open.start);
+                for at in open.breaks {
+                    self.patch(at, self.here());
+                }
+            }
+            _ => {
+                if let Some(x) = self.register_name(token) {
+                    self.assignment(x)?;
+                } else if let Ok(value) = self.literal(token) {
+                    self.out.push(value as u8);
+                } else {
+                    // a bare label calls it
+                    self.pos -= 1;
+                    self.address(0x2000)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn index(&mut self) -> Result<(), String> {
+        match self.next()?.0 {
+            ":=" => match self.peek() {
+                Some("long") => {
+                    self.pos += 1;
+                    self.emit(0xF000);
+                    let (label, line) = self.next()?;
+                    match self.resolve_value(label) {
+                        Some(address) => self.emit(address),
+                        None => {
+                            self.fixups.push(Fixup {
+                                at: self.out.len(),
+                                label,
+                                line,
+                                long: true,
+                            });
+                            self.emit(0);
+                        }
+                    }
+                    Ok(())
+                }
+                Some("hex") => {
+                    self.pos += 1;
+                    self.register_op(0xF029)
+                }
+                Some("bighex") => {
+                    self.pos += 1;
+                    self.register_op(0xF030)
+                }
+                _ => self.address(0xA000),
+            },
+            "+=" => self.register_op(0xF01E),
+            other => Err(format!("expected := or += after i, found {other}")),
+        }
+    }
+
+    fn assignment(&mut self, x: u8) -> Result<(), String> {
+        let x16 = (x as u16) << 8;
+        let op = self.next()?.0;
+        let rhs = self.next()?.0;
+        if let Some(y) = self.register_name(rhs) {
+            let n = match op {
+                ":=" => 0,
+                "|=" => 1,
+                "&=" => 2,
+                "^=" => 3,
+                "+=" => 4,
+                "-=" => 5,
+                ">>=" => 6,
+                "=-" => 7,
+                "<<=" => 0xE,
+                _ => return Err(format!("unknown operator {op}")),
+            };
+            self.emit(0x8000 | x16 | ((y as u16) << 4) | n);
+            return Ok(());
+        }
+
+        let opcode = match (op, rhs) {
+            (":=", "random") => 0xC000 | x16 | self.byte()? as u16,
+            (":=", "delay") => 0xF007 | x16,
+            (":=", "key") => 0xF00A | x16,
+            (":=", _) => 0x6000 | x16 | self.literal(rhs)? as u8 as u16,
+            ("+=", _) => 0x7000 | x16 | self.literal(rhs)? as u8 as u16,
+            ("-=", _) => 0x7000 | x16 | (self.literal(rhs)? as u8).wrapping_neg() as u16,
+            _ => return Err(format!("can't use {op} with {rhs}")),
+        };
+        self.emit(opcode);
+        Ok(())
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let x = self.register()?;
+        match self.next()?.0 {
+            "key" => Ok(Condition::Key(x)),
+            "-key" => Ok(Condition::NotKey(x)),
+            op @ ("==" | "!=") => {
+                let rhs = self.next()?.0;
+                let operand = match self.register_name(rhs) {
+                    Some(y) => Operand::Register(y),
+                    None => Operand::Byte(self.literal(rhs)? as u8),
+                };
+                Ok(if op == "==" {
+                    Condition::Equal(x, operand)
+                } else {
+                    Condition::NotEqual(x, operand)
+                })
+            }
+            other => Err(format!("expected ==, !=, key or -key, found {other}")),
+        }
+    }
+
+    /// Emits `opcode` with the register in the next token as its X.
+    fn register_op(&mut self, opcode: u16) -> Result<(), String> {
+        let x = self.register()? as u16;
+        self.emit(opcode | (x << 8));
+        Ok(())
+    }
+
+    /// Emits `opcode` with the address in the next token as its NNN,
+    /// filling it in later if it's a label that isn't defined yet.
+    fn address(&mut self, opcode: u16) -> Result<(), String> {
+        let (token, line) = self.next()?;
+        match self.resolve_value(token) {
+            Some(address) => self.emit(opcode | (address & 0xFFF)),
+            None if token.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                return Err(format!("invalid number {token}"));
+            }
+            None => {
+                self.fixups.push(Fixup {
+                    at: self.out.len(),
+                    label: token,
+                    line,
+                    long: false,
+                });
+                self.emit(opcode);
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_value(&self, token: &str) -> Option<u16> {
+        self.literal(token)
+            .ok()
+            .or_else(|| self.labels.get(token).copied())
+    }
+
+    /// Fills in every label used before its definition.
+    fn resolve(&mut self) -> Result<(), String> {
+        for fixup in &self.fixups {
+            let address = *self
+                .labels
+                .get(fixup.label)
+                .ok_or_else(|| format!("line {}: unknown label {}", fixup.line, fixup.label))?;
+            if fixup.long {
+                self.out[fixup.at..fixup.at + 2].copy_from_slice(&address.to_be_bytes());
+            } else {
+                let opcode = u16::from_be_bytes([self.out[fixup.at], self.out[fixup.at + 1]]);
+                let opcode = opcode | (address & 0xFFF);
+                self.out[fixup.at..fixup.at + 2].copy_from_slice(&opcode.to_be_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    /// Points the jump at `at` to `address`.
+    fn patch(&mut self, at: usize, address: u16) {
+        let opcode = 0x1000 | (address & 0xFFF);
+        self.out[at..at + 2].copy_from_slice(&opcode.to_be_bytes());
+    }
+
+    fn emit(&mut self, opcode: u16) {
+        self.out.extend_from_slice(&opcode.to_be_bytes());
+    }
+
+    fn here(&self) -> u16 {
+        DEFAULT_LOAD_ADDRESS + self.out.len() as u16
+    }
+
+    fn next(&mut self) -> Result<(&'a str, usize), String> {
+        let token = *self.tokens.get(self.pos).ok_or("unexpected end of file")?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|&(token, _)| token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()?.0 {
+            token if token == expected => Ok(()),
+            token => Err(format!("expected {expected}, found {token}")),
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, String> {
+        Ok(self.next()?.0)
+    }
+
+    fn register(&mut self) -> Result<u8, String> {
+        let token = self.next()?.0;
+        self.register_name(token)
+            .ok_or_else(|| format!("expected a register, found {token}"))
+    }
+
+    /// The register `v0` to `vF`, or an alias for one, named by `token`.
+    fn register_name(&self, token: &str) -> Option<u8> {
+        if let Some(&register) = self.aliases.get(token) {
+            return Some(register);
+        }
+        let digit = token.strip_prefix(['v', 'V'])?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u8::from_str_radix(digit, 16).ok()
+    }
+
+    fn value(&mut self) -> Result<u16, String> {
+        let token = self.next()?.0;
+        self.literal(token)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.value()? as u8)
+    }
+
+    /// A number in decimal, `0x` hex or `0b` binary, negative numbers as
+    /// two's complement, or a constant.
+    fn literal(&self, token: &str) -> Result<u16, String> {
+        if let Some(&value) = self.constants.get(token) {
+            return Ok(value);
+        }
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            u16::from_str_radix(hex, 16)
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            u16::from_str_radix(binary, 2)
+        } else {
+            digits.parse()
+        }
+        .map_err(|_| format!("expected a number, found {token}"))?;
+        Ok(if negative {
+            value.wrapping_neg()
+        } else {
+            value
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jumps_to_main_unless_it_comes_first() {
+        assert_eq!(assemble(": main clear"), Ok(vec![0x00, 0xE0]));
+        assert_eq!(
+            assemble(": helper return : main helper"),
+            Ok(vec![0x12, 0x04, 0x00, 0xEE, 0x22, 0x02])
+        );
+    }
+
+    #[test]
+    fn if_then_skips_unless_the_condition_holds() {
+        let source = ": main
+            if v1 == 5 then clear
+            if v1 != 5 then clear
+            if v1 == v3 then clear
+            if v1 != v3 then clear
+            if v4 key then clear
+            if v4 -key then clear";
+        assert_eq!(
+            assemble(source),
+            Ok(vec![
+                0x41, 0x05, 0x00, 0xE0, 0x31, 0x05, 0x00, 0xE0, 0x91, 0x30, 0x00, 0xE0, 0x51, 0x30,
+                0x00, 0xE0, 0xE4, 0xA1, 0x00, 0xE0, 0xE4, 0x9E, 0x00, 0xE0,
+            ])
+        );
+    }
+
+    #[test]
+    fn begin_skips_a_jump_past_the_block() {
+        assert_eq!(
+            assemble(": main if v0 == 1 begin v1 := 2 else v1 := 3 end clear"),
+            Ok(vec![
+                0x30, 0x01, 0x12, 0x08, 0x61, 0x02, 0x12, 0x0A, 0x61, 0x03, 0x00, 0xE0,
+            ])
+        );
+        assert_eq!(
+            assemble(": main if v0 != v1 begin clear end"),
+            Ok(vec![0x90, 0x10, 0x12, 0x06, 0x00, 0xE0])
+        );
+        assert_eq!(
+            assemble(": main if v2 key begin clear end"),
+            Ok(vec![0xE2, 0x9E, 0x12, 0x06, 0x00, 0xE0])
+        );
+    }
+
+    #[test]
+    fn while_skips_a_jump_out_of_the_loop() {
+        assert_eq!(
+            assemble(": main loop v0 += 1 while v0 != 10 while v1 key again clear"),
+            Ok(vec![
+                0x70, 0x01, 0x40, 0x0A, 0x12, 0x0C, 0xE1, 0x9E, 0x12, 0x0C, 0x12, 0x00, 0x00, 0xE0,
+            ])
+        );
+        assert_eq!(
+            assemble(": main loop while v0 == v1 while v2 -key again"),
+            Ok(vec![
+                0x50, 0x10, 0x12, 0x0A, 0xE2, 0xA1, 0x12, 0x0A, 0x12, 0x00,
+            ])
+        );
+    }
+
+    #[test]
+    fn labels_resolve_before_and_after_their_definition() {
+        assert_eq!(
+            assemble(": main i := data jump main i := long data : data 0xAA"),
+            Ok(vec![0xA2, 0x08, 0x12, 0x00, 0xF0, 0x00, 0x02, 0x08, 0xAA])
+        );
+    }
+
+    #[test]
+    fn bad_labels_are_errors() {
+        assert_eq!(
+            assemble(": main jump nowhere"),
+            Err("line 1: unknown label nowhere".to_string())
+        );
+        assert_eq!(
+            assemble(": main\n: main"),
+            Err("line 2: label main is defined twice".to_string())
+        );
+    }
+}
@@ -0,0 +1,160 @@
+/// Number of instruction categories, one per instruction form plus one for
+/// opcodes that aren't instructions.
+pub const CATEGORIES: usize = 50;
+
+const NAMES: [&str; CATEGORIES] = [
+    "SCD n",
+    "CLS",
+    "RET",
+    "SCR",
+    "SCL",
+    "EXIT",
+    "LOW",
+    "HIGH",
+    "JP addr",
+    "CALL addr",
+    "SE Vx, byte",
+    "SNE Vx, byte",
+    "SE Vx, Vy",
+    "SAVE Vx-Vy",
+    "LOAD Vx-Vy",
+    "LD Vx, byte",
+    "ADD Vx, byte",
+    "LD Vx, Vy",
+    "OR Vx, Vy",
+    "AND Vx, Vy",
+    "XOR Vx, Vy",
+    "ADD Vx, Vy",
+    "SUB Vx, Vy",
+    "SHR Vx, Vy",
+    "SUBN Vx, Vy",
+    "SHL Vx, Vy",
+    "SNE Vx, Vy",
+    "LD I, addr",
+    "JP V0, addr",
+    "RND Vx, byte",
+    "DRW Vx, Vy, n",
+    "SKP Vx",
+    "SKNP Vx",
+    "LD I, LONG",
+    "PLANE n",
+    "AUDIO",
+    "LD Vx, DT",
+    "LD Vx, K",
+    "LD DT, Vx",
+    "LD ST, Vx",
+    "ADD I, Vx",
+    "LD F, Vx",
+    "LD HF, Vx",
+    "LD B, Vx",
+    "PITCH Vx",
+    "LD [I], Vx",
+    "LD Vx, [I]",
+    "LD R, Vx",
+    "LD Vx, R",
+    "DW",
+];
+
+/// The index into `NAMES` of the instruction form of `opcode`.
+fn category(opcode: u16) -> usize {
+    let op = (opcode >> 12) as u8;
+    let n = opcode & 0xF;
+    let value = opcode & 0xFF;
+
+    match (op, value, n) {
+        (0, 0xC0..=0xCF, _) => 0,
+        (0, 0xE0, _) => 1,
+        (0, 0xEE, _) => 2,
+        (0, 0xFB, _) => 3,
+        (0, 0xFC, _) => 4,
+        (0, 0xFD, _) => 5,
+        (0, 0xFE, _) => 6,
+        (0, 0xFF, _) => 7,
+        (1, _, _) => 8,
+        (2, _, _) => 9,
+        (3, _, _) => 10,
+        (4, _, _) => 11,
+        (5, _, 0) => 12,
+        (5, _, 2) => 13,
+        (5, _, 3) => 14,
+        (6, _, _) => 15,
+        (7, _, _) => 16,
+        (8, _, 0) => 17,
+        (8, _, 1) => 18,
+        (8, _, 2) => 19,
+        (8, _, 3) => 20,
+        (8, _, 4) => 21,
+        (8, _, 5) => 22,
+        (8, _, 6) => 23,
+        (8, _, 7) => 24,
+        (8, _, 0xE) => 25,
+        (9, _, 0) => 26,
+        (0xA, _, _) => 27,
+        (0xB, _, _) => 28,
+        (0xC, _, _) => 29,
+        (0xD, _, _) => 30,
+        (0xE, 0x9E, _) => 31,
+        (0xE, 0xA1, _) => 32,
+        (0xF, 0x00, _) if opcode == 0xF000 => 33,
+        (0xF, 0x01, _) => 34,
+        (0xF, 0x02, _) if opcode == 0xF002 => 35,
+        (0xF, 0x07, _) => 36,
+        (0xF, 0x0A, _) => 37,
+        (0xF, 0x15, _) => 38,
+        (0xF, 0x18, _) => 39,
+        (0xF, 0x1E, _) => 40,
+        (0xF, 0x29, _) => 41,
+        (0xF, 0x30, _) => 42,
+        (0xF, 0x33, _) => 43,
+        (0xF, 0x3A, _) => 44,
+        (0xF, 0x55, _) => 45,
+        (0xF, 0x65, _) => 46,
+        (0xF, 0x75, _) => 47,
+        (0xF, 0x85, _) => 48,
+        _ => 49,
+    }
+}
+
+/// Counts how many times each kind of instruction was executed.
+pub struct Profile {
+    counts: [u64; CATEGORIES],
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self {
+            counts: [0; CATEGORIES],
+        }
+    }
+
+    pub fn record(&mut self, opcode: u16) {
+        self.counts[category(opcode)] += 1;
+    }
+
+    /// One line per instruction form that was executed, most frequent
+    /// first, with its count and share of the total.
+    pub fn summary(&self) -> String {
+        let total: u64 = self.counts.iter().sum();
+        let mut counts: Vec<(&str, u64)> = NAMES
+            .iter()
+            .copied()
+            .zip(self.counts)
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1));
+
+        counts
+            .iter()
+            .map(|(name, count)| {
+                let percent = 100.0 * *count as f64 / total as f64;
+                format!("{name:<14} {count:>12} {percent:>6.2}%\n")
+            })
+            .collect()
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
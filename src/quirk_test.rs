@@ -0,0 +1,144 @@
+//! Reads the results off the screen of Timendus' quirks test ROM, which
+//! draws one line per category with a check mark or a cross in it.
+
+use chip8::display::Display;
+
+/// The categories in the order the ROM lists them.
+pub const CATEGORIES: [&str; 6] = [
+    "vF reset",
+    "Memory",
+    "Display wait",
+    "Clipping",
+    "Shifting",
+    "Jumping",
+];
+
+const CHECK: [&str; 4] = ["   #", "  # ", "# # ", " #  "];
+const CROSS: [&str; 4] = ["#  #", " ## ", " ## ", "#  #"];
+
+/// Whether each category passed, or `None` for one whose line has neither
+/// mark. Returns `None` if the screen doesn't have a line per category.
+pub fn results(screen: &Display) -> Option<[Option<bool>; CATEGORIES.len()]> {
+    let lines = lines(screen);
+    if lines.len() != CATEGORIES.len() {
+        return None;
+    }
+    Some(std::array::from_fn(|i| {
+        let (top, bottom) = lines[i];
+        glyphs(screen, top, bottom).iter().find_map(|glyph| {
+            if *glyph == CHECK {
+                Some(true)
+            } else if *glyph == CROSS {
+                Some(false)
+            } else {
+                None
+            }
+        })
+    }))
+}
+
+/// The ranges of rows with something drawn in them, split at empty rows.
+fn lines(screen: &Display) -> Vec<(usize, usize)> {
+    let used = |y: usize| (0..screen.width()).any(|x| screen.pixel(x, y) != 0);
+    let mut lines = Vec::new();
+    let mut start = None;
+    for y in 0..=screen.height() {
+        match (y < screen.height() && used(y), start) {
+            (true, None) => start = Some(y),
+            (false, Some(top)) => {
+                lines.push((top, y));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// The shapes drawn between rows `top` and `bottom`, split at empty columns
+/// and trimmed to the rows they use, as rows of `#` and spaces.
+fn glyphs(screen: &Display, top: usize, bottom: usize) -> Vec<Vec<String>> {
+    let lit = |x: usize, y: usize| screen.pixel(x, y) != 0;
+    let mut glyphs = Vec::new();
+    let mut start = None;
+    for x in 0..=screen.width() {
+        match (
+            x < screen.width() && (top..bottom).any(|y| lit(x, y)),
+            start,
+        ) {
+            (true, None) => start = Some(x),
+            (false, Some(left)) => {
+                let mut rows: Vec<String> = (top..bottom)
+                    .map(|y| {
+                        (left..x)
+                            .map(|x| if lit(x, y) { '#' } else { ' ' })
+                            .collect()
+                    })
+                    .collect();
+                while rows.first().is_some_and(|row| row.trim().is_empty()) {
+                    rows.remove(0);
+                }
+                while rows.last().is_some_and(|row| row.trim().is_empty()) {
+                    rows.pop();
+                }
+                glyphs.push(rows);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    glyphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draws `glyph` with its top left corner at `x`, `y`.
+    fn draw(screen: &mut Display, glyph: &[&str], x: usize, y: usize) {
+        for (dy, row) in glyph.iter().enumerate() {
+            for (dx, c) in row.chars().enumerate() {
+                if c == '#' {
+                    screen.flip(0, x + dx, y + dy);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reads_a_mark_on_each_line() {
+        let label = ["###", "# #", "###"];
+        let mut screen = Display::new();
+        let marks = [true, false, true, true, false, true];
+        for (i, &passed) in marks.iter().enumerate() {
+            let y = i * 5;
+            draw(&mut screen, &label, 2, y);
+            draw(&mut screen, &label, 6, y);
+            if passed {
+                draw(&mut screen, &CHECK, 40, y);
+            } else {
+                draw(&mut screen, &CROSS, 40, y);
+            }
+        }
+        assert_eq!(results(&screen), Some(marks.map(Some)));
+    }
+
+    #[test]
+    fn line_without_a_mark_is_unknown() {
+        let mut screen = Display::new();
+        for i in 0..CATEGORIES.len() {
+            draw(&mut screen, &CHECK, 40, i * 5);
+        }
+        screen.flip(0, 40, 10);
+        let results = results(&screen).unwrap();
+        assert_eq!(results[2], None);
+        assert_eq!(results[1], Some(true));
+    }
+
+    #[test]
+    fn screen_without_a_line_per_category_is_unreadable() {
+        let mut screen = Display::new();
+        draw(&mut screen, &CHECK, 40, 0);
+        assert_eq!(results(&screen), None);
+    }
+}
@@ -0,0 +1,163 @@
+use serde::Deserialize;
+use std::{fmt, str::FromStr};
+
+/// Behaviors that differ between CHIP-8 interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quirks {
+    /// `FX55` and `FX65` leave `I` pointing past the last register accessed,
+    /// as on the COSMAC VIP.
+    pub load_store_increments_i: bool,
+    /// `8XY6` and `8XYE` shift `VY` into `VX` instead of shifting `VX` in place.
+    pub shift_uses_vy: bool,
+    /// `8XY6` and `8XYE` write the shifted out bit to `VF` before the result,
+    /// so `VF` ends up holding the result when `X` is `F`, as some older
+    /// emulators did. Which register is shifted still follows
+    /// `shift_uses_vy`.
+    pub shift_sets_flag_first: bool,
+    /// `DXYN` waits for the vertical blank interrupt before drawing, which
+    /// limits sprites to one per frame.
+    pub display_wait: bool,
+    /// `BNNN` is read as `BXNN` and jumps to `XNN` plus `VX` instead of
+    /// `NNN` plus `V0`, as on the SUPER-CHIP.
+    pub jump_offset_uses_vx: bool,
+    /// `DXYN` wraps sprites around the edges of the screen instead of
+    /// clipping them, as on XO-CHIP.
+    pub wrap_sprites: bool,
+    /// `DXYN` sets `VF` to the number of sprite rows that collided or were
+    /// clipped at the bottom of the screen instead of just 0 or 1.
+    pub count_collided_rows: bool,
+    /// `DXYN` uses up more of the frame the taller the sprite is, roughly
+    /// like drawing took longer on the COSMAC VIP.
+    pub draw_timing: bool,
+    /// How many subroutine calls can be nested before `2NNN` overflows the
+    /// stack.
+    pub stack_size: usize,
+    /// Memory is 64 KB instead of 4 KB, as on XO-CHIP.
+    pub extended_memory: bool,
+    /// XO-CHIP's `5XY2`, `5XY3`, `F000`, `F002`, `FN01` and `FX3A` run
+    /// instead of being invalid.
+    pub xo_chip_opcodes: bool,
+}
+
+impl Quirks {
+    /// Bytes of memory, including the 512 below the program.
+    pub fn memory_size(&self) -> usize {
+        if self.extended_memory {
+            0x10000
+        } else {
+            0x1000
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            load_store_increments_i: true,
+            shift_uses_vy: true,
+            shift_sets_flag_first: false,
+            display_wait: false,
+            jump_offset_uses_vx: false,
+            wrap_sprites: false,
+            count_collided_rows: false,
+            draw_timing: false,
+            stack_size: 12,
+            extended_memory: false,
+            xo_chip_opcodes: false,
+        }
+    }
+}
+
+/// A family of interpreters with a common set of quirks.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Platform {
+    #[default]
+    #[serde(rename = "chip8")]
+    Chip8,
+    /// The original COSMAC VIP interpreter, which waits for vertical blank
+    /// before drawing and takes longer to draw taller sprites.
+    #[serde(rename = "vip")]
+    CosmacVip,
+    #[serde(rename = "schip")]
+    SuperChip,
+    #[serde(rename = "xochip")]
+    XoChip,
+}
+
+impl Platform {
+    /// The behavior programs written for this platform expect.
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Self::Chip8 => Quirks::default(),
+            Self::CosmacVip => Quirks {
+                display_wait: true,
+                draw_timing: true,
+                ..Quirks::default()
+            },
+            Self::SuperChip => Quirks {
+                load_store_increments_i: false,
+                shift_uses_vy: false,
+                shift_sets_flag_first: false,
+                display_wait: false,
+                jump_offset_uses_vx: true,
+                wrap_sprites: false,
+                count_collided_rows: false,
+                draw_timing: false,
+                stack_size: 16,
+                extended_memory: false,
+                xo_chip_opcodes: false,
+            },
+            Self::XoChip => Quirks {
+                load_store_increments_i: true,
+                shift_uses_vy: true,
+                shift_sets_flag_first: false,
+                display_wait: false,
+                jump_offset_uses_vx: false,
+                wrap_sprites: true,
+                count_collided_rows: true,
+                draw_timing: false,
+                stack_size: 16,
+                extended_memory: true,
+                xo_chip_opcodes: true,
+            },
+        }
+    }
+
+    /// A speed most programs written for the platform expect.
+    pub fn ipf(self) -> u32 {
+        match self {
+            Self::Chip8 => 12,
+            Self::CosmacVip => 15,
+            Self::SuperChip => 30,
+            Self::XoChip => 1000,
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chip8" => Ok(Self::Chip8),
+            "vip" => Ok(Self::CosmacVip),
+            "schip" => Ok(Self::SuperChip),
+            "xochip" => Ok(Self::XoChip),
+            _ => Err(format!(
+                "invalid platform {s}: expected chip8, vip, schip or xochip"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Chip8 => write!(f, "CHIP-8"),
+            Self::CosmacVip => write!(f, "COSMAC VIP"),
+            Self::SuperChip => write!(f, "SUPER-CHIP"),
+            Self::XoChip => write!(f, "XO-CHIP"),
+        }
+    }
+}
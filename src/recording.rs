@@ -0,0 +1,60 @@
+use crate::display::{Display, HEIGHT, WIDTH};
+use gif::{Encoder, Frame, Repeat};
+use std::{borrow::Cow, error::Error, fs::File, path::Path};
+
+/// Roughly a minute of continuous drawing at 60 frames per second.
+pub const MAX_FRAMES: usize = 3600;
+
+/// A screen captured at the window resolution as palette indices.
+pub struct Capture {
+    /// The frame number it was displayed at.
+    at: u64,
+    pixels: Vec<u8>,
+}
+
+impl Capture {
+    pub fn new(at: u64, screen: &Display) -> Self {
+        let scale = WIDTH / screen.width();
+        let mut pixels = Vec::with_capacity(WIDTH * HEIGHT);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                pixels.push(screen.pixel(x / scale, y / scale));
+            }
+        }
+        Self { at, pixels }
+    }
+}
+
+/// Encodes `captures` as a looping GIF. Each capture stays on screen until
+/// the next one, and the last one until frame `end`.
+pub fn save_gif(
+    path: &Path,
+    captures: &[Capture],
+    palette: [u32; 4],
+    end: u64,
+) -> Result<(), Box<dyn Error>> {
+    let rgb: Vec<u8> = palette
+        .iter()
+        .flat_map(|color| color.to_be_bytes()[1..].to_vec())
+        .collect();
+
+    let mut encoder = Encoder::new(File::create(path)?, WIDTH as u16, HEIGHT as u16, &rgb)?;
+    encoder.set_repeat(Repeat::Infinite)?;
+
+    // delays are in hundredths of a second, so round the running total
+    // rather than each delay to avoid drifting
+    let centis = |frame: u64| (frame * 100 + 30) / 60;
+
+    for (i, capture) in captures.iter().enumerate() {
+        let next = captures.get(i + 1).map_or(end, |next| next.at);
+        let frame = Frame {
+            width: WIDTH as u16,
+            height: HEIGHT as u16,
+            delay: (centis(next) - centis(capture.at)) as u16,
+            buffer: Cow::Borrowed(&capture.pixels),
+            ..Default::default()
+        };
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Packs the keypad state into a mask with bit `i` set if key `i` is down.
+fn pack(keys: &[bool; 16]) -> u16 {
+    keys.iter()
+        .enumerate()
+        .filter(|&(_, &down)| down)
+        .map(|(i, _)| 1u16 << i)
+        .sum()
+}
+
+fn unpack(mask: u16) -> [bool; 16] {
+    std::array::from_fn(|i| mask & (1 << i) != 0)
+}
+
+/// Writes the keypad state of every emulated frame to a file, one hex mask
+/// per line.
+pub struct InputRecorder {
+    out: BufWriter<File>,
+}
+
+impl InputRecorder {
+    pub fn create(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    pub fn record(&mut self, keys: &[bool; 16]) -> Result<(), Box<dyn Error>> {
+        writeln!(self.out, "{:04X}", pack(keys))?;
+        Ok(())
+    }
+}
+
+/// Plays back the keypad states written by an [`InputRecorder`].
+pub struct InputReplay {
+    frames: Vec<u16>,
+    next: usize,
+}
+
+impl InputReplay {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let frames = contents
+            .lines()
+            .enumerate()
+            .map(|(i, line)| {
+                u16::from_str_radix(line.trim(), 16)
+                    .map_err(|_| format!("invalid keys on line {} of replay: {line}", i + 1))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { frames, next: 0 })
+    }
+
+    /// The keypad state for the next frame, or `None` once the replay has
+    /// run out.
+    pub fn next_frame(&mut self) -> Option<[bool; 16]> {
+        let mask = *self.frames.get(self.next)?;
+        self.next += 1;
+        Some(unpack(mask))
+    }
+}
@@ -0,0 +1,170 @@
+#[cfg(feature = "octo")]
+use crate::octo;
+use chip8::{cpu::MAX_PROGRAM_SIZE, error::Chip8Error};
+use std::{
+    error::Error,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// File extensions of the ROMs picked up from a directory.
+const EXTENSIONS: [&str; 6] = ["ch8", "c8", "sc8", "xo8", "rom", "8o"];
+
+/// Replaces each directory in `sources` with the ROMs inside it, sorted by
+/// name. Returns the list and whether any directory was expanded.
+pub fn expand(sources: Vec<String>) -> Result<(Vec<String>, bool), Box<dyn Error>> {
+    let mut roms = Vec::new();
+    let mut expanded = false;
+    for source in sources {
+        if source == "-" || is_url(&source) || !Path::new(&source).is_dir() {
+            roms.push(source);
+            continue;
+        }
+
+        let mut found = Vec::new();
+        for entry in fs::read_dir(&source)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            if path.is_file() && EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()) {
+                found.push(path.to_string_lossy().into_owned());
+            }
+        }
+        if found.is_empty() {
+            return Err(format!("no ROMs found in {source}").into());
+        }
+        found.sort();
+        roms.extend(found);
+        expanded = true;
+    }
+    Ok((roms, expanded))
+}
+
+/// Reads a ROM from a file, from stdin if `source` is `-`, or from the web
+/// if it's an HTTP URL. Files ending in `.8o` are assembled as Octo source.
+pub fn load(source: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let program = if source == "-" {
+        read_capped(io::stdin())?
+    } else if is_url(source) {
+        fetch(source)?
+    } else if source.ends_with(".8o") {
+        assemble(source)?
+    } else {
+        fs::read(source)?
+    };
+
+    if program.len() > MAX_PROGRAM_SIZE {
+        return Err(Chip8Error::RomTooLarge {
+            size: program.len(),
+            max: MAX_PROGRAM_SIZE,
+        }
+        .into());
+    }
+    Ok(program)
+}
+
+/// Overwrites the bytes at memory address `address` of a program loaded at
+/// `load_address` with `bytes`, a string of hex digits, growing the program
+/// if the patch runs past its end. Only the program itself can be patched.
+pub fn patch(
+    program: &mut Vec<u8>,
+    load_address: u16,
+    address: u16,
+    bytes: &str,
+) -> Result<(), Box<dyn Error>> {
+    if bytes.len() % 2 != 0 {
+        return Err(format!("{bytes}: odd number of hex digits").into());
+    }
+    let bytes = (0..bytes.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&bytes[i..i + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{bytes}: {e}"))?;
+
+    let start = address.checked_sub(load_address).ok_or_else(|| {
+        format!("{address:03X}: patches must start at the load address {load_address:03X} or later")
+    })? as usize;
+    let end = start + bytes.len();
+    if load_address as usize + end > 0x10000 {
+        return Err(format!("{address:03X}: patch runs past the end of memory").into());
+    }
+
+    if program.len() < end {
+        program.resize(end, 0);
+    }
+    program[start..end].copy_from_slice(&bytes);
+    Ok(())
+}
+
+/// Where to save the machine state for a ROM loaded from `source`. ROMs
+/// from stdin or the web save to the current directory.
+pub fn state_path(source: &str) -> PathBuf {
+    if source == "-" {
+        PathBuf::from("stdin.state")
+    } else if is_url(source) {
+        let name = source.rsplit('/').find(|s| !s.is_empty()).unwrap_or("rom");
+        PathBuf::from(format!("{name}.state"))
+    } else {
+        PathBuf::from(format!("{source}.state"))
+    }
+}
+
+/// A short name for the ROM loaded from `source` to show to the user.
+pub fn name(source: &str) -> &str {
+    if source == "-" {
+        "stdin"
+    } else if is_url(source) {
+        source.rsplit('/').find(|s| !s.is_empty()).unwrap_or(source)
+    } else {
+        Path::new(source)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(source)
+    }
+}
+
+/// When the ROM file at `source` was last changed, or `None` if it isn't a
+/// file or that can't be told.
+pub fn modified(source: &str) -> Option<SystemTime> {
+    if source == "-" || is_url(source) {
+        return None;
+    }
+    fs::metadata(source).and_then(|m| m.modified()).ok()
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+/// Reads at most one byte more than fits in memory, which is enough to tell
+/// that a ROM is too large without reading all of it.
+fn read_capped(reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut program = Vec::new();
+    reader
+        .take(MAX_PROGRAM_SIZE as u64 + 1)
+        .read_to_end(&mut program)?;
+    Ok(program)
+}
+
+#[cfg(feature = "octo")]
+fn assemble(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let source = fs::read_to_string(path)?;
+    Ok(octo::assemble(&source).map_err(|e| format!("{path}: {e}"))?)
+}
+
+#[cfg(not(feature = "octo"))]
+fn assemble(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err(format!("unable to assemble {path}: built without the octo feature").into())
+}
+
+#[cfg(feature = "net")]
+fn fetch(url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let response = ureq::get(url).call()?;
+    Ok(read_capped(response.into_reader())?)
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch(url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    Err(format!("unable to fetch {url}: built without the net feature").into())
+}
@@ -0,0 +1,88 @@
+use chip8::{cpu::Cpu, keypad::Keypad};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    error::Error,
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The same layout as the window, on the left side of a QWERTY keyboard.
+const KEYS: [char; 16] = [
+    'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+];
+
+/// Terminals only report key presses, so a key counts as held for this many
+/// frames after each press or repeat.
+const HOLD_FRAMES: u8 = 6;
+
+const FRAME: Duration = Duration::from_micros(16_667);
+
+/// Plays the program loaded in `cpu` in the terminal until `Esc` or `Ctrl+C`
+/// is pressed or it exits with `00FD`. There is no sound.
+pub fn run(mut cpu: Cpu, ipf: u32) -> Result<(), Box<dyn Error>> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+    let result = play(&mut cpu, ipf);
+    execute!(io::stdout(), cursor::Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn play(cpu: &mut Cpu, ipf: u32) -> Result<(), Box<dyn Error>> {
+    let mut stdout = io::stdout();
+    let mut held = [0u8; 16];
+    let mut keypad = Keypad::default();
+
+    loop {
+        let start = Instant::now();
+
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(KeyEvent {
+                code,
+                modifiers,
+                kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                ..
+            }) = event::read()?
+            else {
+                continue;
+            };
+
+            match code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                KeyCode::Char(c) => {
+                    if let Some(key) = KEYS.iter().position(|&k| k == c.to_ascii_lowercase()) {
+                        held[key] = HOLD_FRAMES;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let keys = held.map(|frames| frames > 0);
+        for frames in &mut held {
+            *frames = frames.saturating_sub(1);
+        }
+
+        keypad.update(keys);
+        cpu.step_frame(&keypad, ipf)?;
+        if cpu.screen.take_dirty() != 0 {
+            for (y, line) in cpu.screen.render_ascii().lines().enumerate() {
+                queue!(stdout, cursor::MoveTo(0, y as u16), Print(line))?;
+            }
+            stdout.flush()?;
+        }
+        if cpu.exited() {
+            return Ok(());
+        }
+
+        thread::sleep(FRAME.saturating_sub(start.elapsed()));
+    }
+}
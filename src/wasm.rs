@@ -0,0 +1,90 @@
+use crate::{
+    cpu::Cpu,
+    display::{HEIGHT, WIDTH},
+    error::Chip8Error,
+    keypad::Keypad,
+    quirks::{Platform, Quirks},
+};
+use wasm_bindgen::prelude::*;
+
+/// The machine as seen from JavaScript, which is left to draw the
+/// framebuffer and play the beep.
+#[wasm_bindgen]
+pub struct Emulator {
+    cpu: Cpu,
+    keys: [bool; 16],
+    keypad: Keypad,
+    ipf: u32,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(rom: &[u8]) -> Result<Emulator, JsError> {
+        Ok(Self {
+            cpu: Cpu::new(rom, Quirks::default(), None)
+                .map_err(|e| JsError::new(&e.to_string()))?,
+            keys: [false; 16],
+            keypad: Keypad::default(),
+            ipf: Platform::default().ipf(),
+        })
+    }
+
+    /// Runs one 60 Hz frame, unless the program has exited. Invalid
+    /// instructions are skipped like on the desktop.
+    pub fn step_frame(&mut self) -> Result<(), JsError> {
+        if self.cpu.exited() {
+            return Ok(());
+        }
+        self.keypad.update(self.keys);
+        self.cpu.start_frame(self.ipf);
+        while !self.cpu.frame_done() {
+            match self.cpu.step(&self.keypad) {
+                Ok(_) | Err(Chip8Error::InvalidOpcode { .. }) => {}
+                Err(e) => return Err(JsError::new(&e.to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn key_down(&mut self, key: u8) {
+        self.keys[key as usize & 0xF] = true;
+    }
+
+    pub fn key_up(&mut self, key: u8) {
+        self.keys[key as usize & 0xF] = false;
+    }
+
+    /// Whether the program has exited with `00FD`, after which stepping
+    /// does nothing.
+    pub fn exited(&self) -> bool {
+        self.cpu.exited()
+    }
+
+    /// Whether the beep should be playing.
+    pub fn sound(&self) -> bool {
+        self.cpu.sound > 0
+    }
+
+    pub fn width(&self) -> usize {
+        WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        HEIGHT
+    }
+
+    /// The screen as one color index per pixel, row by row, at the high
+    /// resolution. Low resolution pixels are doubled.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        let screen = &self.cpu.screen;
+        let scale = WIDTH / screen.width();
+        let mut pixels = Vec::with_capacity(WIDTH * HEIGHT);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                pixels.push(screen.pixel(x / scale, y / scale));
+            }
+        }
+        pixels
+    }
+}
@@ -0,0 +1,165 @@
+//! Runs programs for a fixed number of frames and compares the screen with
+//! the one checked in under `tests/golden`. The goldens are written and
+//! checked by hand, so the programs here are given as opcodes rather than
+//! built with the crate's own assembler.
+//!
+//! ROMs put in `tests/roms` are run the same way against the golden with
+//! the same name.
+
+use chip8::{Cpu, Keypad, Platform};
+use std::{fs, path::PathBuf};
+
+/// Frames to run each program for, long enough for all of them to finish.
+const FRAMES: usize = 60;
+
+/// Every font digit, 8 to a row.
+#[rustfmt::skip]
+const DIGITS: &[u8] = &[
+    0x60, 0x00, // 200  LD V0, 0
+    0x61, 0x01, // 202  LD V1, 1
+    0x62, 0x01, // 204  LD V2, 1
+    0xF0, 0x29, // 206  LD F, V0
+    0xD1, 0x25, // 208  DRW V1, V2, 5
+    0x70, 0x01, // 20A  ADD V0, 1
+    0x71, 0x05, // 20C  ADD V1, 5
+    0x31, 0x29, // 20E  SE V1, 41
+    0x12, 0x16, // 210  JP 216
+    0x61, 0x01, // 212  LD V1, 1
+    0x72, 0x06, // 214  ADD V2, 6
+    0x30, 0x10, // 216  SE V0, 16
+    0x12, 0x06, // 218  JP 206
+    0x12, 0x1A, // 21A  JP 21A
+];
+
+/// The result and flag of 8XY4, 8XY5, 8XY7 and 8XYE, one per row.
+#[rustfmt::skip]
+const ARITHMETIC: &[u8] = &[
+    0x6A, 0x01, // 200  LD VA, 1
+    0x6B, 0x01, // 202  LD VB, 1
+    0x60, 0xC8, // 204  LD V0, 200
+    0x61, 0x64, // 206  LD V1, 100
+    0x80, 0x14, // 208  ADD V0, V1
+    0x22, 0x24, // 20A  CALL 224
+    0x60, 0x64, // 20C  LD V0, 100
+    0x61, 0xC8, // 20E  LD V1, 200
+    0x80, 0x15, // 210  SUB V0, V1
+    0x22, 0x24, // 212  CALL 224
+    0x60, 0x64, // 214  LD V0, 100
+    0x61, 0x1E, // 216  LD V1, 30
+    0x80, 0x17, // 218  SUBN V0, V1
+    0x22, 0x24, // 21A  CALL 224
+    0x60, 0x81, // 21C  LD V0, 0x81
+    0x80, 0x0E, // 21E  SHL V0, V0
+    0x22, 0x24, // 220  CALL 224
+    0x12, 0x22, // 222  JP 222
+    // shows V0 and VF at VA, VB, then moves down a row
+    0x85, 0xF0, // 224  LD V5, VF
+    0x84, 0x00, // 226  LD V4, V0
+    0x22, 0x34, // 228  CALL 234
+    0x84, 0x50, // 22A  LD V4, V5
+    0x22, 0x34, // 22C  CALL 234
+    0x6A, 0x01, // 22E  LD VA, 1
+    0x7B, 0x07, // 230  ADD VB, 7
+    0x00, 0xEE, // 232  RET
+    // shows V4 as three decimal digits at VA, VB
+    0xA2, 0x4E, // 234  LD I, 24E
+    0xF4, 0x33, // 236  LD B, V4
+    0xF2, 0x65, // 238  LD V2, [I]
+    0xF0, 0x29, // 23A  LD F, V0
+    0xDA, 0xB5, // 23C  DRW VA, VB, 5
+    0x7A, 0x05, // 23E  ADD VA, 5
+    0xF1, 0x29, // 240  LD F, V1
+    0xDA, 0xB5, // 242  DRW VA, VB, 5
+    0x7A, 0x05, // 244  ADD VA, 5
+    0xF2, 0x29, // 246  LD F, V2
+    0xDA, 0xB5, // 248  DRW VA, VB, 5
+    0x7A, 0x07, // 24A  ADD VA, 7
+    0x00, 0xEE, // 24C  RET
+    0x00, 0x00, 0x00, // 24E  scratch for the digits
+];
+
+/// SUPER-CHIP's big digits in high resolution, scrolled down and right.
+#[rustfmt::skip]
+const BIG_DIGITS: &[u8] = &[
+    0x00, 0xFF, // 200  HIGH
+    0x60, 0x00, // 202  LD V0, 0
+    0x61, 0x00, // 204  LD V1, 0
+    0x62, 0x00, // 206  LD V2, 0
+    0xF0, 0x30, // 208  LD HF, V0
+    0xD1, 0x2A, // 20A  DRW V1, V2, 10
+    0x70, 0x01, // 20C  ADD V0, 1
+    0x71, 0x0A, // 20E  ADD V1, 10
+    0x30, 0x0A, // 210  SE V0, 10
+    0x12, 0x08, // 212  JP 208
+    0x00, 0xC4, // 214  SCD 4
+    0x00, 0xFB, // 216  SCR
+    0x12, 0x18, // 218  JP 218
+];
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn check(name: &str, program: &[u8], platform: Platform) {
+    let mut cpu = Cpu::new(program, platform.quirks(), Some(0)).unwrap();
+    let keypad = Keypad::default();
+    for _ in 0..FRAMES {
+        cpu.step_frame(&keypad, platform.ipf()).unwrap();
+    }
+    let actual = cpu.screen.render_ascii();
+
+    let path = golden_dir().join(format!("{name}.txt"));
+    let expected =
+        fs::read_to_string(&path).unwrap_or_else(|e| panic!("can't read {}: {e}", path.display()));
+    if actual != expected {
+        panic!(
+            "{name} doesn't match {}:\n{}",
+            path.display(),
+            diff(&expected, &actual)
+        );
+    }
+}
+
+/// The two screens line by line, with `-` before expected rows and `+`
+/// before the actual rows that differ from them.
+fn diff(expected: &str, actual: &str) -> String {
+    let mut out = String::new();
+    for (expected, actual) in expected.lines().zip(actual.lines()) {
+        if expected == actual {
+            out += &format!(" |{expected}|\n");
+        } else {
+            out += &format!("-|{expected}|\n+|{actual}|\n");
+        }
+    }
+    out
+}
+
+#[test]
+fn digits() {
+    check("digits", DIGITS, Platform::Chip8);
+}
+
+#[test]
+fn arithmetic() {
+    check("arithmetic", ARITHMETIC, Platform::Chip8);
+}
+
+#[test]
+fn big_digits() {
+    check("big_digits", BIG_DIGITS, Platform::SuperChip);
+}
+
+#[test]
+fn roms() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/roms");
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return;
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "ch8") {
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            check(name, &fs::read(&path).unwrap(), Platform::Chip8);
+        }
+    }
+}